@@ -0,0 +1,60 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252, X_USER_DEFINED};
+
+/// How far into a document to look for a `<meta>` charset declaration, as
+/// in the HTML encoding sniffing algorithm.
+const PRESCAN_LIMIT: usize = 1024;
+
+/// Decodes an HTML document. The encoding is taken from a byte order mark,
+/// the declared charset (e.g. from a Content-Type header) or a `<meta>` tag,
+/// in that order, and defaults to UTF-8. Invalid sequences become U+FFFD
+/// rather than failing the document.
+pub fn decode_html(bytes: &[u8], charset: Option<&str>) -> String {
+    let encoding = Encoding::for_bom(bytes)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| charset.and_then(|label| Encoding::for_label(label.trim().as_bytes())))
+        .or_else(|| meta_charset(bytes))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+/// The charset parameter of a Content-Type header value.
+pub fn content_type_charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+    })
+}
+
+/// The encoding declared by `<meta charset>` or `<meta http-equiv
+/// content="...; charset=...">` near the start of a document.
+fn meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(PRESCAN_LIMIT)]).to_ascii_lowercase();
+
+    for tag in head.split("<meta").skip(1) {
+        let tag = tag.split('>').next().unwrap_or_default();
+        let Some(index) = tag.find("charset") else {
+            continue;
+        };
+        let Some(value) = tag[index + "charset".len()..].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let label = value
+            .trim_start()
+            .trim_start_matches(['"', '\''])
+            .split(|c: char| matches!(c, '"' | '\'' | ';' | '/') || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+
+        // A document that could be read this far isn't really UTF-16, and
+        // x-user-defined is treated as Windows-1252 by browsers
+        return Encoding::for_label(label.as_bytes()).map(|encoding| match encoding {
+            encoding if encoding == UTF_16LE || encoding == UTF_16BE => UTF_8,
+            encoding if encoding == X_USER_DEFINED => WINDOWS_1252,
+            encoding => encoding,
+        });
+    }
+    None
+}
@@ -0,0 +1,40 @@
+use anyhow::{Context, Result};
+use arboard::Clipboard;
+
+/// Reads HTML from the system clipboard, falling back to plain text for
+/// clipboards that only hold HTML source copied as text.
+pub fn read_html() -> Result<String> {
+    let mut clipboard = Clipboard::new().context("Failed to access the clipboard")?;
+    let html = match clipboard.get().html() {
+        Ok(html) => html,
+        Err(_) => clipboard
+            .get_text()
+            .context("The clipboard holds neither HTML nor text")?,
+    };
+    Ok(strip_cf_html_header(&html).to_string())
+}
+
+pub fn write_text(text: String) -> Result<()> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .context("Failed to write to the clipboard")
+}
+
+/// Windows wraps clipboard HTML in a CF_HTML description whose header gives
+/// the byte offsets of the actual document.
+fn strip_cf_html_header(html: &str) -> &str {
+    if !html.starts_with("Version:") {
+        return html;
+    }
+
+    let offset = |key: &str| {
+        html.lines()
+            .take_while(|line| !line.starts_with('<'))
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse::<usize>().ok())
+    };
+    match (offset("StartHTML:"), offset("EndHTML:")) {
+        (Some(start), Some(end)) => html.get(start..end).unwrap_or(html),
+        _ => html,
+    }
+}
@@ -0,0 +1,110 @@
+use clap::Args;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::{HashSet, VecDeque};
+
+use crate::http::Client;
+use crate::input::{self, InputArgs, Source, SourceNamer};
+use crate::Extraction;
+
+#[derive(Args)]
+pub struct CrawlArgs {
+    /// URL to start crawling from
+    pub seed: Url,
+
+    /// How many links away from the seed page to follow
+    #[arg(long, default_value_t = 1)]
+    pub depth: usize,
+}
+
+/// Visits pages breadth-first from the seed, following links to the same
+/// site as the seed (where it redirects to, if anywhere) up to the
+/// configured depth, and extracts tables from every page.
+/// Linked pages are only followed when they match `--include-pattern`,
+/// and no page is fetched that the site's robots.txt disallows.
+pub async fn run(
+    args: &CrawlArgs,
+    input_args: &InputArgs,
+    client: &Client,
+    extraction: &mut Extraction<'_>,
+) {
+    let mut namer = SourceNamer::default();
+    let mut visited = HashSet::new();
+    let mut site = args.seed.host_str().map(str::to_string);
+    let mut queue = VecDeque::from([(normalize(args.seed.clone()), 0)]);
+    visited.insert(queue[0].0.clone());
+
+    while let Some((url, depth)) = queue.pop_front() {
+        if !client.allowed_by_robots(url.as_str()).await {
+            eprintln!("Skipping {}: disallowed by robots.txt", url);
+            continue;
+        }
+
+        // Only the seed is requested with the configured method and body
+        let mut source = Source {
+            linked: depth > 0,
+            ..namer.source(url.as_str())
+        };
+        let html = match input::load(&mut source, input_args, client).await {
+            Ok(html) => html,
+            Err(err) => {
+                extraction.failed(&source, err);
+                continue;
+            }
+        };
+
+        // Relative links are relative to where the page was redirected to
+        let base = match source.final_url.as_deref().and_then(|final_url| Url::parse(final_url).ok()) {
+            Some(final_url) => {
+                visited.insert(normalize(final_url.clone()));
+                if depth == 0 {
+                    site = final_url.host_str().map(str::to_string);
+                }
+                final_url
+            }
+            None => url,
+        };
+        if depth < args.depth {
+            for link in links(&html, &base) {
+                let on_site = link.host_str().zip(site.as_deref()).is_some_and(|(a, b)| same_site(a, b));
+                let included = input_args.include_pattern.as_ref().is_none_or(|pattern| pattern.is_match(link.as_str()));
+                if on_site && included && visited.insert(link.clone()) {
+                    queue.push_back((link, depth + 1));
+                }
+            }
+        }
+
+        extraction.process(&source, &html);
+        if input_args.follow_iframes {
+            extraction.process_frames(input::load_iframes(&source, &html, input_args, client).await);
+        }
+    }
+}
+
+/// Absolute http(s) URLs of all links on a page.
+fn links(html: &str, base: &Url) -> Vec<Url> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("a[href]").unwrap();
+
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .filter_map(|href| base.join(href.trim()).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .map(normalize)
+        .collect()
+}
+
+/// Whether two hosts belong to the same site, counting `www.example.com`
+/// and `example.com` as one.
+fn same_site(a: &str, b: &str) -> bool {
+    let strip = |host: &str| host.strip_prefix("www.").unwrap_or(host).to_ascii_lowercase();
+    strip(a) == strip(b)
+}
+
+/// Drops the fragment so links to different anchors of a page are only
+/// visited once.
+fn normalize(mut url: Url) -> Url {
+    url.set_fragment(None);
+    url
+}
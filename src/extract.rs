@@ -0,0 +1,476 @@
+use anyhow::{bail, Result};
+use clap::{Args, ValueEnum};
+use regex::Regex;
+use ego_tree::iter::Edge;
+use reqwest::Url;
+use scraper::{ElementRef, Node, Selector};
+use std::sync::LazyLock;
+
+use crate::select::parse_selector;
+use crate::{Table, TABLE_ELEMENTS};
+
+/// Options for which elements are extracted as tables.
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// Also extract definition lists (`<dl>`) as two-column tables of terms
+    /// and definitions
+    #[arg(long)]
+    pub extract_dl: bool,
+
+    /// Also extract lists (`<ul>`, `<ol>`) as tables with one row per item
+    #[arg(long)]
+    pub extract_lists: bool,
+
+    /// Only extract the lists matching this CSS selector, e.g. `.features`
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_selector, requires = "extract_lists")]
+    pub list_selector: Option<Selector>,
+
+    /// How to lay out the items of nested lists
+    #[arg(long, value_enum, default_value_t = NestedLists::Indent, requires = "extract_lists")]
+    pub nested_lists: NestedLists,
+
+    /// Remove reference markers such as `[1]` and `†`, and footnote `<sup>`
+    /// elements such as `<sup>[a]</sup>`, from cell text
+    #[arg(long)]
+    pub strip_footnotes: bool,
+
+    /// Read form controls in cells: the value of inputs, `true`/`false` for
+    /// checkboxes and radio buttons, and the selected option of selects
+    #[arg(long)]
+    pub form_values: bool,
+
+    /// Leave out rows, cells and text hidden with the `hidden` attribute,
+    /// `aria-hidden="true"` or an inline `display: none` style
+    #[arg(long, overrides_with = "include_hidden")]
+    pub exclude_hidden: bool,
+
+    /// Keep hidden rows, cells and text, as by default
+    #[arg(long, overrides_with = "exclude_hidden")]
+    pub include_hidden: bool,
+
+    /// Keep the text of `<script>`, `<style>`, `<template>` and `<noscript>`
+    /// elements inside cells, which is left out by default
+    #[arg(long)]
+    pub keep_script_text: bool,
+
+    /// Whether cells hold their text or their inner HTML, as is; the other
+    /// cell content options only apply to text
+    #[arg(long, value_enum, default_value_t = CellContent::Text)]
+    pub cell_content: CellContent,
+
+    /// Use the first of these attributes found on a cell, or else on an
+    /// element inside it, as its value instead of its text, e.g.
+    /// `data-sort-value,data-value`
+    #[arg(long, value_name = "ATTRS", value_delimiter = ',')]
+    pub prefer_attr: Vec<String>,
+
+    /// Text to put in place of line breaks (`<br>`) inside cells, e.g. `; `
+    /// or `\n` for a newline; by default they are dropped
+    #[arg(long, value_name = "TEXT", value_parser = parse_escapes)]
+    pub br_as: Option<String>,
+
+    /// How to keep the links in cells: as their URL in place of the text,
+    /// as Markdown links, or in a `<column>_url` column next to the text
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub links: Option<LinkMode>,
+
+    /// What to do with tables whose rows have different numbers of cells
+    #[arg(long, value_enum, default_value_t = Ragged::Pad)]
+    pub ragged: Ragged,
+
+    /// Longest cell text allowed, in characters, before `--cell-overflow`
+    /// applies, for cells that swallowed a whole page through bad markup
+    #[arg(long, value_name = "N")]
+    pub max_cell_length: Option<usize>,
+
+    /// What to do with cells longer than `--max-cell-length`: cut them,
+    /// fail the document, or keep them with a warning
+    #[arg(long, value_enum, default_value_t = Overflow::Truncate, requires = "max_cell_length")]
+    pub cell_overflow: Overflow,
+
+    /// What to do with `<tfoot>` rows such as totals: keep them as data,
+    /// write them to a companion `_totals` table, or leave them out
+    #[arg(long, value_enum, default_value_t = Tfoot::Include)]
+    pub tfoot: Tfoot,
+
+    /// Extract each `<tbody>` of a table as a table of its own, named after
+    /// its group heading row (a single header or spanning cell) if it starts
+    /// with one
+    #[arg(long, conflicts_with = "group_column")]
+    pub split_by_tbody: bool,
+
+    /// Add a first `group` column with the text of the group heading row
+    /// (a single header or spanning cell) above each row in its `<tbody>`
+    #[arg(long)]
+    pub group_column: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Ragged {
+    /// Pad short rows with empty cells to the widest row
+    Pad,
+    /// Make every row as wide as the first, cutting longer rows
+    Truncate,
+    /// Fail the document, as well as for cells spanning past the table
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Overflow {
+    Truncate,
+    Error,
+    Keep,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Tfoot {
+    Include,
+    Separate,
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CellContent {
+    Text,
+    Html,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LinkMode {
+    KeepHref,
+    Markdown,
+    ExtraColumn,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum NestedLists {
+    /// One column, with nested items indented by two spaces per level
+    Indent,
+    /// One column per level, with a row for each innermost item
+    Columns,
+}
+
+impl ExtractArgs {
+    /// Applies `--cell-overflow` to the cells longer than
+    /// `--max-cell-length`. Kept cells are reported on stderr.
+    pub fn limit_cell_length(&self, table: &mut Table) -> Result<()> {
+        let Some(max) = self.max_cell_length else {
+            return Ok(());
+        };
+        for (r, row) in table.rows.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                let length = cell.chars().count();
+                if length <= max {
+                    continue;
+                }
+                match self.cell_overflow {
+                    Overflow::Truncate => *cell = cell.chars().take(max).collect(),
+                    Overflow::Error => bail!("Cell in row {}, column {} has {} characters", r + 1, c + 1, length),
+                    Overflow::Keep => eprintln!(
+                        "Keeping a cell of {} characters in table {} of {}, row {}, column {}",
+                        length,
+                        table.index,
+                        table.source,
+                        r + 1,
+                        c + 1
+                    ),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Selector matching every element to extract as a table.
+    pub fn table_selector(&self) -> String {
+        let mut selector = TABLE_ELEMENTS.to_string();
+        if self.extract_dl {
+            selector.push_str(", dl");
+        }
+        if self.extract_lists {
+            selector.push_str(", ul, ol");
+        }
+        selector
+    }
+
+    /// Whether an element matching the table selector is extracted. Lists
+    /// nested in an extracted list are part of its table instead.
+    pub fn keeps(&self, element: ElementRef) -> bool {
+        let is_list = |element: &ElementRef| matches!(element.value().name(), "ul" | "ol");
+        let is_extracted_list = |element: &ElementRef| {
+            is_list(element) && self.list_selector.as_ref().is_none_or(|selector| selector.matches(element))
+        };
+        !is_list(&element)
+            || (is_extracted_list(&element)
+                && !element.ancestors().filter_map(ElementRef::wrap).any(|ancestor| is_extracted_list(&ancestor)))
+    }
+
+    /// Whether a row or cell is left out of its table. Rows are also left
+    /// out when a row group containing them is hidden.
+    pub fn skips(&self, element: ElementRef, table: ElementRef) -> bool {
+        self.exclude_hidden
+            && element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .take_while(|ancestor| ancestor.id() != table.id())
+                .chain([element])
+                .any(is_hidden)
+    }
+
+    /// The text of a cell, trimmed, or with `--links keep-href` the targets
+    /// of its links unless it's a header cell.
+    pub fn cell_text(&self, cell: ElementRef, base: Option<&Url>) -> String {
+        if self.cell_content == CellContent::Html {
+            return cell.inner_html().trim().to_string();
+        }
+        for name in &self.prefer_attr {
+            let element = cell.descendent_elements().find(|element| element.value().attr(name).is_some());
+            if let Some(value) = element.and_then(|element| element.value().attr(name)) {
+                return value.trim().to_string();
+            }
+        }
+        if self.links == Some(LinkMode::KeepHref) && cell.value().name() != "th" {
+            let links = self.cell_links(cell, base);
+            if !links.is_empty() {
+                return links.join(" ");
+            }
+        }
+        self.text_within(cell, base, |_| false).trim().to_string()
+    }
+
+    /// The targets of the links in a cell, resolved against `base`.
+    pub fn cell_links(&self, cell: ElementRef, base: Option<&Url>) -> Vec<String> {
+        let links = Selector::parse("a[href]").unwrap();
+        cell.select(&links)
+            .filter(|link| {
+                let ancestors = link.ancestors().take_while(|ancestor| ancestor.id() != cell.id());
+                !(self.strip_footnotes && ancestors.filter_map(ElementRef::wrap).any(is_footnote))
+            })
+            .filter_map(|link| link.value().attr("href"))
+            .map(|href| resolve(href.trim(), base))
+            .collect()
+    }
+
+    /// The text of an element, leaving out that of the descendants matching
+    /// `skip` and, with `--strip-footnotes`, reference markers.
+    fn text_within(&self, element: ElementRef, base: Option<&Url>, skip: impl Fn(&ElementRef) -> bool) -> String {
+        let markdown_links = self.links == Some(LinkMode::Markdown);
+        let mut text = String::new();
+        for edge in element.traverse() {
+            let (node, open) = match edge {
+                Edge::Open(node) => (node, true),
+                Edge::Close(node) => (node, false),
+            };
+            let href = ElementRef::wrap(node)
+                .filter(|element| markdown_links && element.value().name() == "a")
+                .and_then(|link| link.value().attr("href"));
+            let content = match (node.value(), href) {
+                (Node::Text(content), _) if open && self.strip_footnotes => &*FOOTNOTE_MARKER.replace_all(content, ""),
+                (Node::Text(content), _) if open => &**content,
+                (Node::Element(br), _) if open && br.name() == "br" => match &self.br_as {
+                    Some(separator) => separator,
+                    None => continue,
+                },
+                (Node::Element(control), _) if open && self.form_values && matches!(control.name(), "input" | "select") => {
+                    &form_value(ElementRef::wrap(node).unwrap())
+                }
+                (_, Some(_)) if open => "[",
+                (_, Some(href)) => &format!("]({})", resolve(href.trim(), base)),
+                _ => continue,
+            };
+            let skipped = node
+                .ancestors()
+                .take_while(|ancestor| ancestor.id() != element.id())
+                .filter_map(ElementRef::wrap)
+                .any(|ancestor| {
+                    skip(&ancestor)
+                        || (self.strip_footnotes && is_footnote(ancestor))
+                        || (self.form_values && ancestor.value().name() == "select")
+                        || (self.exclude_hidden && is_hidden(ancestor))
+                        || (!self.keep_script_text
+                            && matches!(ancestor.value().name(), "script" | "style" | "template" | "noscript"))
+                });
+            if !skipped {
+                text.push_str(content);
+            }
+        }
+        text
+    }
+
+    /// Rows of a list, laid out as set by `--nested-lists`.
+    pub fn list_rows(&self, list: ElementRef, base: Option<&Url>) -> Vec<Vec<String>> {
+        let mut items = Vec::new();
+        self.list_items(list, base, &mut Vec::new(), &mut items);
+
+        match self.nested_lists {
+            NestedLists::Indent => {
+                let mut rows = vec![vec!["Item".to_string()]];
+                rows.extend(items.into_iter().map(|(path, _)| {
+                    let depth = path.len() - 1;
+                    vec![format!("{}{}", "  ".repeat(depth), path[depth])]
+                }));
+                rows
+            }
+            NestedLists::Columns => {
+                let depth = items.iter().map(|(path, _)| path.len()).max().unwrap_or(1);
+                let mut rows = vec![(1..=depth).map(|level| format!("Level {}", level)).collect()];
+                rows.extend(items.into_iter().filter(|(_, has_children)| !has_children).map(|(mut path, _)| {
+                    path.resize(depth, String::new());
+                    path
+                }));
+                rows
+            }
+        }
+    }
+
+    /// Collects the items of a list in document order, each with the texts
+    /// of its enclosing items and whether it holds a nested list.
+    fn list_items(
+        &self,
+        list: ElementRef,
+        base: Option<&Url>,
+        parents: &mut Vec<String>,
+        items: &mut Vec<(Vec<String>, bool)>,
+    ) {
+        for item in list.children().filter_map(ElementRef::wrap).filter(|child| child.value().name() == "li") {
+            // Lists nested directly in this item, possibly wrapped in other elements
+            let lists = Selector::parse("ul, ol").unwrap();
+            let nested: Vec<ElementRef> = item
+                .select(&lists)
+                .filter(|nested| {
+                    let parent_list = nested.ancestors().filter_map(ElementRef::wrap).find(|ancestor| lists.matches(ancestor));
+                    parent_list.map(|parent| parent.id()) == Some(list.id())
+                })
+                .collect();
+
+            parents.push(self.item_text(item, base));
+            items.push((parents.clone(), !nested.is_empty()));
+            for list in nested {
+                self.list_items(list, base, parents, items);
+            }
+            parents.pop();
+        }
+    }
+
+    /// The text of a list item without that of its nested lists, with
+    /// whitespace collapsed.
+    fn item_text(&self, item: ElementRef, base: Option<&Url>) -> String {
+        let text = self.text_within(item, base, |element| matches!(element.value().name(), "ul" | "ol"));
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Rows of a definition list: a `Term`/`Definition` header, then one row
+    /// per term with the definitions that follow it joined by `; `. Terms and
+    /// definitions may be wrapped in `<div>`s, as HTML allows.
+    pub fn definition_rows(&self, list: ElementRef, base: Option<&Url>) -> Vec<Vec<String>> {
+        let items = list.children().filter_map(ElementRef::wrap).flat_map(|child| {
+            let wrapped: Vec<ElementRef> = if child.value().name() == "div" {
+                child.children().filter_map(ElementRef::wrap).collect()
+            } else {
+                vec![child]
+            };
+            wrapped
+        });
+
+        // Groups of consecutive terms and the definitions following them
+        let mut groups: Vec<(Vec<String>, Vec<String>)> = Vec::new();
+        for item in items {
+            let text = self.cell_text(item, base);
+            match item.value().name() {
+                "dt" => match groups.last_mut() {
+                    Some((_, definitions)) if definitions.is_empty() => groups.last_mut().unwrap().0.push(text),
+                    _ => groups.push((vec![text], Vec::new())),
+                },
+                "dd" => match groups.last_mut() {
+                    Some((_, definitions)) => definitions.push(text),
+                    None => groups.push((Vec::new(), vec![text])),
+                },
+                _ => {}
+            }
+        }
+
+        let mut rows = vec![vec!["Term".to_string(), "Definition".to_string()]];
+        for (terms, definitions) in groups {
+            let definition = definitions.join("; ");
+            if terms.is_empty() {
+                rows.push(vec![String::new(), definition.clone()]);
+            }
+            for term in terms {
+                rows.push(vec![term, definition.clone()]);
+            }
+        }
+        rows
+    }
+}
+
+/// Whether an element is hidden by its attributes or inline style.
+fn is_hidden(element: ElementRef) -> bool {
+    let element = element.value();
+    let style = element.attr("style").unwrap_or_default().to_ascii_lowercase().replace(char::is_whitespace, "");
+    element.attr("hidden").is_some()
+        || element.attr("aria-hidden").is_some_and(|hidden| hidden.trim().eq_ignore_ascii_case("true"))
+        || style.contains("display:none")
+        || style.contains("visibility:hidden")
+}
+
+/// The current value of a form control. Hidden inputs and buttons hold no
+/// data worth keeping.
+fn form_value(control: ElementRef) -> String {
+    let element = control.value();
+    if element.name() == "select" {
+        let options: Vec<ElementRef> = control.select(&Selector::parse("option").unwrap()).collect();
+        let mut selected: Vec<ElementRef> =
+            options.iter().copied().filter(|option| option.value().attr("selected").is_some()).collect();
+        // A single-choice select shows its first option when none is selected
+        if selected.is_empty() && element.attr("multiple").is_none() {
+            selected.extend(options.first());
+        }
+        let texts: Vec<String> = selected
+            .iter()
+            .map(|option| option.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        return texts.join(", ");
+    }
+
+    match element.attr("type").unwrap_or("text").to_ascii_lowercase().as_str() {
+        "checkbox" | "radio" => element.attr("checked").is_some().to_string(),
+        "hidden" | "submit" | "reset" | "button" | "image" | "password" => String::new(),
+        _ => element.attr("value").unwrap_or_default().to_string(),
+    }
+}
+
+/// A link target made absolute, when the document's URL is known.
+fn resolve(href: &str, base: Option<&Url>) -> String {
+    base.and_then(|base| base.join(href).ok()).map_or_else(|| href.to_string(), String::from)
+}
+
+/// Turns `\n` and `\t` typed on the command line into a newline and a tab.
+fn parse_escapes(value: &str) -> Result<String, String> {
+    Ok(value.replace("\\n", "\n").replace("\\t", "\t"))
+}
+
+/// Reference markers removed from any text: `[1]`, `[note 3]`,
+/// `[citation needed]` and daggers.
+static FOOTNOTE_MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(?:\d{1,3}|[a-zA-Z]{1,5} ?\d{1,3}|citation needed)\]|[†‡]").unwrap()
+});
+
+/// Reference markers that only count as such on their own in a `<sup>`:
+/// the above, as well as `[a]`, `§` and `¶`, which are content elsewhere,
+/// e.g. in `[x]` or `§ 12`.
+static SUP_FOOTNOTE_MARKER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[(?:\d{1,3}|[a-zA-Z]|[a-zA-Z]{1,5} ?\d{1,3}|citation needed)\]|[†‡§¶]").unwrap()
+});
+
+/// Whether an element is a footnote reference: a `<sup>` marked as one by
+/// its class, or holding nothing but a marker.
+fn is_footnote(element: ElementRef) -> bool {
+    if element.value().name() != "sup" {
+        return false;
+    }
+    let marked = element
+        .value()
+        .classes()
+        .any(|class| class.contains("reference") || class.contains("footnote") || class == "noprint");
+    let text = element.text().collect::<String>();
+    marked || SUP_FOOTNOTE_MARKER.replace_all(text.trim(), "").is_empty() && !text.trim().is_empty()
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Responses stored on disk together with the validators needed to ask the
+/// server whether they changed.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Validators {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf) -> Result<Cache> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+        Ok(Cache { dir })
+    }
+
+    /// Conditional request headers for a previously stored response, if
+    /// there is one.
+    pub fn conditional_headers(&self, url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(validators) = self.validators(url) else {
+            return headers;
+        };
+
+        let header = |value: Option<String>| value.and_then(|value| HeaderValue::from_str(&value).ok());
+        if let Some(etag) = header(validators.etag) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = header(validators.last_modified) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+
+    /// The stored body of a URL, after the server answered 304 Not Modified.
+    pub fn body(&self, url: &str) -> Result<String> {
+        let path = self.path(url, "body");
+        fs::read_to_string(&path).with_context(|| format!("Failed to read cached response: {}", path.display()))
+    }
+
+    /// Stores a response, as long as it carries validators to revalidate it
+    /// with later.
+    pub fn store(&self, url: &str, headers: &HeaderMap, body: &str) -> Result<()> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = Validators {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        };
+        if validators.etag.is_none() && validators.last_modified.is_none() {
+            return Ok(());
+        }
+
+        fs::write(self.path(url, "body"), body)?;
+        fs::write(self.path(url, "json"), serde_json::to_vec_pretty(&validators)?)?;
+        Ok(())
+    }
+
+    fn validators(&self, url: &str) -> Option<Validators> {
+        let json = fs::read(self.path(url, "json")).ok()?;
+        let validators: Validators = serde_json::from_slice(&json).ok()?;
+        // Guard against hash collisions
+        (validators.url == url && self.path(url, "body").is_file()).then_some(validators)
+    }
+
+    /// Cache entries are named after a stable 64-bit FNV-1a hash of the URL.
+    fn path(&self, url: &str, extension: &str) -> PathBuf {
+        let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        self.dir.join(format!("{:016x}.{}", hash, extension))
+    }
+}
@@ -0,0 +1,734 @@
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use reqwest::cookie::Jar;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, LOCATION, USER_AGENT};
+use reqwest::{redirect, Certificate, Identity, Method, RequestBuilder, Response, StatusCode, Url};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::charset;
+use cache::Cache;
+use robots::Robots;
+use session::Session;
+
+mod cache;
+mod robots;
+mod session;
+
+/// Options for the HTTP requests made to fetch documents.
+#[derive(Args)]
+pub struct HttpArgs {
+    /// User-Agent header sent with every request
+    #[arg(long, global = true, conflicts_with = "ua_preset")]
+    pub user_agent: Option<String>,
+
+    /// Identify as a common desktop browser, for sites that reject unknown clients
+    #[arg(long, global = true, value_enum)]
+    pub ua_preset: Option<UaPreset>,
+
+    /// Basic authentication credentials; without `:PASS` the password is
+    /// read from the `TABLE_RUSTRACTOR_PASSWORD` environment variable
+    #[arg(long, global = true, value_name = "USER[:PASS]", conflicts_with = "auth_bearer")]
+    pub auth_basic: Option<String>,
+
+    /// Bearer token sent in the Authorization header
+    #[arg(long, global = true, value_name = "TOKEN")]
+    pub auth_bearer: Option<String>,
+
+    /// Cookie sent with every request; can be repeated
+    #[arg(long = "cookie", global = true, value_name = "NAME=VALUE")]
+    pub cookies: Vec<String>,
+
+    /// Netscape-format cookies.txt file, as exported by browser extensions
+    #[arg(long, global = true)]
+    pub cookie_file: Option<PathBuf>,
+
+    /// How many times to retry requests that time out, fail to connect or
+    /// get a 429 or 5xx response
+    #[arg(long, global = true, default_value_t = 0)]
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled for every further attempt
+    /// (e.g. `500ms`, `2s`)
+    #[arg(long, global = true, value_parser = parse_duration, default_value = "1s")]
+    pub retry_delay: Duration,
+
+    /// Give up connecting to a server after this long; `0` waits forever
+    #[arg(long, global = true, value_parser = parse_duration, default_value = "10s")]
+    pub connect_timeout: Duration,
+
+    /// Give up on a request, including reading the response, after this
+    /// long; `0` waits forever
+    #[arg(long, global = true, value_parser = parse_duration, default_value = "60s")]
+    pub timeout: Duration,
+
+    /// Minimum time between two requests to the same host
+    #[arg(long, global = true, value_parser = parse_duration, conflicts_with = "rate")]
+    pub delay: Option<Duration>,
+
+    /// Maximum number of requests per second to the same host
+    #[arg(long, global = true)]
+    pub rate: Option<f64>,
+
+    /// Abort downloads larger than this (e.g. `500k`, `20MB`)
+    #[arg(long, global = true, value_name = "SIZE", value_parser = parse_size)]
+    pub max_download_size: Option<u64>,
+
+    /// Keep fetched pages here and only download them again when the server
+    /// reports a change; unchanged pages aren't re-extracted
+    #[arg(long, global = true)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// HTTP method used to fetch input URLs; POST when a body is given
+    #[arg(long, global = true, value_enum, ignore_case = true)]
+    pub method: Option<HttpMethod>,
+
+    /// Form body (`name=value&...`) sent with requests for input URLs
+    #[arg(long, global = true, conflicts_with = "data_json")]
+    pub data: Option<String>,
+
+    /// JSON body sent with requests for input URLs
+    #[arg(long, global = true, value_name = "JSON")]
+    pub data_json: Option<String>,
+
+    /// Maximum number of redirects followed for a request
+    #[arg(long, global = true, default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Treat redirects as errors instead of following them
+    #[arg(long, global = true, conflicts_with = "max_redirects")]
+    pub no_follow_redirects: bool,
+
+    /// Accept invalid TLS certificates, e.g. self-signed ones
+    #[arg(long, global = true)]
+    pub insecure: bool,
+
+    /// PEM file with extra CA certificates to trust
+    #[arg(long, global = true, value_name = "FILE")]
+    pub cacert: Option<PathBuf>,
+
+    /// PEM client certificate for servers that require mutual TLS
+    #[arg(long, global = true, value_name = "FILE", requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate, in PKCS#8 format
+    #[arg(long, global = true, value_name = "FILE", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// TOML file describing requests to run before fetching anything, such
+    /// as a form login whose cookies are then used for every document
+    #[arg(long, global = true, value_name = "FILE")]
+    pub session: Option<PathBuf>,
+
+    /// Crawl and sitemap pages disallowed by the site's robots.txt, and
+    /// ignore its crawl-delay
+    #[arg(long, global = true)]
+    pub ignore_robots: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum UaPreset {
+    Chrome,
+    Firefox,
+    Safari,
+    Edge,
+}
+
+impl UaPreset {
+    fn user_agent(self) -> &'static str {
+        match self {
+            UaPreset::Chrome => {
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36"
+            }
+            UaPreset::Firefox => {
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:143.0) Gecko/20100101 Firefox/143.0"
+            }
+            UaPreset::Safari => {
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/26.0 Safari/605.1.15"
+            }
+            UaPreset::Edge => {
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/141.0.0.0 Safari/537.36 Edg/141.0.0.0"
+            }
+        }
+    }
+}
+
+/// Used when neither `--user-agent` nor `--ua-preset` is given.
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Upper bound for the wait between retries, whatever the server asks for.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Redirects followed to find a robots.txt, the minimum RFC 9309 asks for.
+const MAX_ROBOTS_REDIRECTS: usize = 5;
+
+/// Environment variable holding the password for `--auth-basic USER`.
+const PASSWORD_VAR: &str = "TABLE_RUSTRACTOR_PASSWORD";
+
+enum Auth {
+    Basic { user: String, password: String },
+    Bearer(String),
+}
+
+/// What a request is for, which decides what is sent along with it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Purpose {
+    /// An input document, requested with the configured method and body
+    Document,
+    /// Anything else from the sites being read, including documents reached
+    /// through links, requested with GET
+    Resource,
+    /// A third-party service such as the Wayback Machine, which gets
+    /// neither the credentials nor the `--cookie` cookies
+    ThirdParty,
+}
+
+/// HTTP client shared by everything that fetches documents, configured
+/// from the command line.
+pub struct Client {
+    inner: reqwest::Client,
+    auth: Option<Auth>,
+    /// Cookies from the command line, which apply to every host
+    cookies: Vec<String>,
+    jar: Arc<Jar>,
+    retries: u32,
+    retry_delay: Duration,
+    /// Minimum interval between requests to a host, and when each host may
+    /// be contacted next
+    interval: Option<Duration>,
+    next_request: Mutex<HashMap<String, Instant>>,
+    cache: Option<Cache>,
+    /// Method and body, with its content type, for requesting input documents
+    method: Method,
+    body: Option<(&'static str, String)>,
+    /// Product token matched against robots.txt user-agent lines, unless
+    /// robots.txt is ignored
+    robots_agent: Option<String>,
+    /// Parsed robots.txt of every origin checked so far
+    robots: Mutex<HashMap<String, Arc<Robots>>>,
+    session: Option<Session>,
+    max_download_size: Option<u64>,
+}
+
+/// The body of a fetched document.
+pub struct Fetched {
+    pub text: String,
+    /// URL the document was fetched from after following redirects
+    pub url: String,
+    /// Whether the server confirmed that the cached copy is still current
+    pub unchanged: bool,
+}
+
+impl Client {
+    pub fn new(args: &HttpArgs) -> Result<Client> {
+        let user_agent = match (&args.user_agent, args.ua_preset) {
+            (Some(user_agent), _) => user_agent.as_str(),
+            (None, Some(preset)) => preset.user_agent(),
+            (None, None) => DEFAULT_USER_AGENT,
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).context("Invalid User-Agent")?,
+        );
+
+        let auth = if let Some(credentials) = &args.auth_basic {
+            let (user, password) = match credentials.split_once(':') {
+                Some((user, password)) => (user.to_string(), password.to_string()),
+                None => {
+                    let password = std::env::var(PASSWORD_VAR).with_context(|| {
+                        format!("--auth-basic without a password needs {} to be set", PASSWORD_VAR)
+                    })?;
+                    (credentials.clone(), password)
+                }
+            };
+            Some(Auth::Basic { user, password })
+        } else {
+            args.auth_bearer.clone().map(Auth::Bearer)
+        };
+
+        let interval = match (args.delay, args.rate) {
+            (Some(delay), _) => Some(delay),
+            (None, Some(rate)) if rate > 0.0 && rate.is_finite() => Some(Duration::from_secs_f64(1.0 / rate)),
+            (None, Some(rate)) => bail!("--rate must be a positive number, got {}", rate),
+            (None, None) => None,
+        };
+
+        let body = match (&args.data, &args.data_json) {
+            (Some(data), _) => Some(("application/x-www-form-urlencoded", data.clone())),
+            (None, Some(json)) => {
+                serde_json::from_str::<serde_json::Value>(json).context("Invalid JSON in --data-json")?;
+                Some(("application/json", json.clone()))
+            }
+            (None, None) => None,
+        };
+        let method = match args.method {
+            Some(HttpMethod::Get) if body.is_some() => bail!("--data and --data-json can't be sent with GET"),
+            Some(HttpMethod::Get) => Method::GET,
+            Some(HttpMethod::Post) => Method::POST,
+            None if body.is_some() => Method::POST,
+            None => Method::GET,
+        };
+
+        let session = match &args.session {
+            Some(path) => Some(
+                Session::load(path)
+                    .with_context(|| format!("Failed to read session file: {}", path.display()))?,
+            ),
+            None => None,
+        };
+
+        let robots_agent = (!args.ignore_robots)
+            .then(|| user_agent.split(['/', ' ']).next().unwrap_or(user_agent).to_string());
+
+        let jar = Arc::new(Jar::default());
+        if let Some(path) = &args.cookie_file {
+            load_cookie_file(path, &jar)
+                .with_context(|| format!("Failed to read cookie file: {}", path.display()))?;
+        }
+
+        let redirect = if args.no_follow_redirects {
+            redirect::Policy::none()
+        } else {
+            redirect::Policy::limited(args.max_redirects)
+        };
+        let mut builder = reqwest::Client::builder()
+            .default_headers(headers)
+            .cookie_provider(jar.clone())
+            .redirect(redirect);
+        if !args.connect_timeout.is_zero() {
+            builder = builder.connect_timeout(args.connect_timeout);
+        }
+        if !args.timeout.is_zero() {
+            builder = builder.timeout(args.timeout);
+        }
+        if args.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(path) = &args.cacert {
+            let certificates = load_certificates(path)
+                .with_context(|| format!("Failed to read CA certificates: {}", path.display()))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if let (Some(cert), Some(key)) = (&args.client_cert, &args.client_key) {
+            let identity = load_identity(cert, key)
+                .with_context(|| format!("Failed to load client certificate: {}", cert.display()))?;
+            builder = builder.identity(identity);
+        }
+        let inner = builder.build()?;
+        Ok(Client {
+            inner,
+            auth,
+            cookies: args.cookies.clone(),
+            jar,
+            retries: args.retries,
+            retry_delay: args.retry_delay,
+            interval,
+            next_request: Mutex::new(HashMap::new()),
+            cache: args.cache_dir.clone().map(Cache::new).transpose()?,
+            method,
+            body,
+            robots_agent,
+            robots: Mutex::new(HashMap::new()),
+            session,
+            max_download_size: args.max_download_size,
+        })
+    }
+
+    /// Sends a request for the given purpose. Transient failures are
+    /// retried with jittered exponential backoff; the final response is
+    /// returned whatever its status.
+    async fn send(&self, url: &str, headers: HeaderMap, purpose: Purpose) -> Result<Response> {
+        unfollowed_redirect(url, self.send_allowing_redirects(url, headers, purpose).await?)
+    }
+
+    /// Sends a request like `send`, but returns redirect responses that
+    /// weren't followed rather than failing on them.
+    async fn send_allowing_redirects(&self, url: &str, headers: HeaderMap, purpose: Purpose) -> Result<Response> {
+        if purpose != Purpose::ThirdParty {
+            self.add_cookies(url);
+        }
+
+        let mut attempt = 0;
+        loop {
+            self.wait_for_turn(url).await;
+            let result = self.request(url, purpose).headers(headers.clone()).send().await;
+            let retry = match &result {
+                Ok(response) if is_transient(response.status()) => {
+                    Some((response.status().to_string(), retry_after(response)))
+                }
+                Err(err) if err.is_timeout() || err.is_connect() || err.is_request() => {
+                    Some((err.to_string(), None))
+                }
+                _ => None,
+            };
+
+            match retry {
+                Some((reason, wait)) if attempt < self.retries => {
+                    let delay = wait.unwrap_or_else(|| self.backoff(attempt)).min(MAX_RETRY_DELAY);
+                    eprintln!("Retrying {} in {:.1}s after {}", url, delay.as_secs_f64(), reason);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    /// Puts the `--cookie` cookies into the jar for the URL's host.
+    fn add_cookies(&self, url: &str) {
+        if let Ok(parsed) = Url::parse(url) {
+            for cookie in &self.cookies {
+                self.jar.add_cookie_str(cookie, &parsed);
+            }
+        }
+    }
+
+    fn request(&self, url: &str, purpose: Purpose) -> RequestBuilder {
+        let mut request = match (purpose, &self.body) {
+            (Purpose::Document, Some((content_type, body))) => self
+                .inner
+                .request(self.method.clone(), url)
+                .header(CONTENT_TYPE, *content_type)
+                .body(body.clone()),
+            (Purpose::Document, None) => self.inner.request(self.method.clone(), url),
+            (Purpose::ThirdParty, _) => return self.inner.get(url),
+            (Purpose::Resource, _) => self.inner.get(url),
+        };
+
+        // Credentials are added per request rather than as default headers
+        // so that reqwest drops them when redirected to another host
+        match &self.auth {
+            Some(Auth::Basic { user, password }) => request = request.basic_auth(user, Some(password)),
+            Some(Auth::Bearer(token)) => request = request.bearer_auth(token),
+            None => {}
+        }
+        request
+    }
+
+    /// Waits until the URL's host may be contacted again under `--delay` or
+    /// `--rate`. Each caller reserves the next free slot, so concurrent
+    /// requests to one host are spaced out too.
+    async fn wait_for_turn(&self, url: &str) {
+        let Ok(url) = Url::parse(url) else {
+            return;
+        };
+        let Some(host) = url.host_str().map(str::to_string) else {
+            return;
+        };
+        let crawl_delay = self
+            .robots
+            .lock()
+            .unwrap()
+            .get(&url.origin().ascii_serialization())
+            .and_then(|robots| robots.crawl_delay);
+        let Some(interval) = self.interval.max(crawl_delay) else {
+            return;
+        };
+
+        let slot = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_request.get(&host).map_or(now, |&next| next.max(now));
+            next_request.insert(host, slot + interval);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+
+    /// Delay before retry number `attempt + 1`, randomized by ±50% so that
+    /// concurrent requests don't retry in lockstep.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.retry_delay.saturating_mul(2u32.saturating_pow(attempt));
+        exponential.mul_f64(0.5 + fastrand::f64())
+    }
+
+    /// Whether the site's robots.txt lets this client fetch a URL. The file
+    /// is fetched once per origin; a missing one allows everything and an
+    /// unreachable one nothing. Its crawl-delay then applies to every later
+    /// request to the origin.
+    pub async fn allowed_by_robots(&self, url: &str) -> bool {
+        let Some(agent) = &self.robots_agent else {
+            return true;
+        };
+        let Ok(url) = Url::parse(url) else {
+            return true;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            return true;
+        }
+
+        let origin = url.origin().ascii_serialization();
+        let cached = self.robots.lock().unwrap().get(&origin).cloned();
+        let robots = match cached {
+            Some(robots) => robots,
+            None => {
+                let robots = Arc::new(self.fetch_robots(&origin, agent).await);
+                self.robots.lock().unwrap().insert(origin, robots.clone());
+                robots
+            }
+        };
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        robots.allows(&path)
+    }
+
+    /// Fetches the robots.txt of an origin. Redirects are followed even with
+    /// `--no-follow-redirects`, as RFC 9309 asks, and one that leads nowhere
+    /// counts as a missing file.
+    async fn fetch_robots(&self, origin: &str, agent: &str) -> Robots {
+        let mut url = format!("{}/robots.txt", origin);
+        let mut redirects = 0;
+        let response = loop {
+            let response = match self.send_allowing_redirects(&url, HeaderMap::new(), Purpose::Resource).await {
+                Ok(response) => response,
+                Err(_) => return Robots::disallow_all(),
+            };
+            let target = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok());
+            match target {
+                Some(target) if response.status().is_redirection() && redirects < MAX_ROBOTS_REDIRECTS => {
+                    url = target.into();
+                    redirects += 1;
+                }
+                _ => break response,
+            }
+        };
+        let status = response.status();
+        if status.is_client_error() || status.is_redirection() {
+            return Robots::default();
+        }
+        match response.text().await {
+            Ok(content) if status.is_success() => Robots::parse(&content, agent),
+            _ => Robots::disallow_all(),
+        }
+    }
+
+    /// The body of a response, decoded using the charset from its
+    /// Content-Type header or else the document's own declaration. Reading
+    /// stops as soon as the body outgrows `--max-download-size`.
+    async fn body_text(&self, mut response: Response) -> Result<String> {
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let url = response.url().clone();
+        let too_large = |size: u64| self.max_download_size.is_some_and(|limit| size > limit);
+
+        if response.content_length().is_some_and(too_large) {
+            bail!("{} is larger than the maximum download size", url);
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+            if too_large(bytes.len() as u64) {
+                bail!("Aborted download of {} after exceeding the maximum download size", url);
+            }
+        }
+        Ok(charset::decode_html(&bytes, content_type.as_deref().and_then(charset::content_type_charset)))
+    }
+
+    /// Sends a request without the configured credentials, for third-party
+    /// services that shouldn't see them.
+    pub async fn get_without_auth(&self, url: &str) -> Result<Response> {
+        self.send(url, HeaderMap::new(), Purpose::ThirdParty).await
+    }
+
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        Ok(self.fetch_with(url, Purpose::Resource).await?.text)
+    }
+
+    /// Fetches an input document with the configured method and body.
+    pub async fn fetch(&self, url: &str) -> Result<Fetched> {
+        self.fetch_with(url, Purpose::Document).await
+    }
+
+    /// Fetches a document reached through another one, such as a linked
+    /// page or an iframe, with GET.
+    pub async fn fetch_linked(&self, url: &str) -> Result<Fetched> {
+        self.fetch_with(url, Purpose::Resource).await
+    }
+
+    /// Fetches a document from a third-party archive, without the
+    /// configured method, body, credentials or cookies.
+    pub async fn fetch_archived(&self, url: &str) -> Result<Fetched> {
+        self.fetch_with(url, Purpose::ThirdParty).await
+    }
+
+    /// Fetches a page, revalidating the cached copy with a conditional
+    /// request when `--cache-dir` is set. Responses to POST requests depend
+    /// on more than the URL and aren't cached.
+    async fn fetch_with(&self, url: &str, purpose: Purpose) -> Result<Fetched> {
+        let cache = self.cache.as_ref().filter(|_| purpose != Purpose::Document || self.method == Method::GET);
+        let Some(cache) = cache else {
+            let response = self.send(url, HeaderMap::new(), purpose).await?.error_for_status()?;
+            let final_url = response.url().to_string();
+            let text = self.body_text(response).await?;
+            return Ok(Fetched {
+                text,
+                url: final_url,
+                unchanged: false,
+            });
+        };
+
+        let response = self.send(url, cache.conditional_headers(url), purpose).await?;
+        let final_url = response.url().to_string();
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched {
+                text: cache.body(url)?,
+                url: final_url,
+                unchanged: true,
+            });
+        }
+
+        let response = response.error_for_status()?;
+        let headers = response.headers().clone();
+        let text = self.body_text(response).await?;
+        cache.store(url, &headers, &text)?;
+        Ok(Fetched {
+            text,
+            url: final_url,
+            unchanged: false,
+        })
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Fails on a redirect response, which only gets this far when
+/// `--no-follow-redirects` is set.
+fn unfollowed_redirect(url: &str, response: Response) -> Result<Response> {
+    let status = response.status();
+    if !status.is_redirection() || status == StatusCode::NOT_MODIFIED {
+        return Ok(response);
+    }
+    let location = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .unwrap_or_default();
+    bail!("{} redirects to {} ({}), which isn't followed", url, location, status)
+}
+
+/// The wait requested by a `Retry-After` header given in seconds.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let seconds = value.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Parses a duration such as `250ms`, `2s`, `1.5m` or `1h`; plain numbers
+/// are seconds.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}', expected e.g. 500ms or 2s", value))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        other => return Err(format!("unknown duration unit '{}', expected ms, s, m or h", other)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    Ok(Certificate::from_pem_bundle(&fs::read(path)?)?)
+}
+
+fn load_identity(cert: &Path, key: &Path) -> Result<Identity> {
+    Ok(Identity::from_pkcs8_pem(&fs::read(cert)?, &fs::read(key)?)?)
+}
+
+/// Parses a byte size such as `800`, `500k`, `20MB` or `1GiB`; units are
+/// powers of 1024.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}', expected e.g. 500k or 20MB", value))?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let multiplier: u64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        _ => return Err(format!("unknown size unit '{}', expected k, M or G", unit)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// Adds the cookies of a Netscape cookies.txt file to the jar. Each line
+/// holds the domain, whether subdomains match, path, secure flag, expiry,
+/// name and value, separated by tabs; expired cookies are skipped.
+fn load_cookie_file(path: &Path, jar: &Jar) -> Result<()> {
+    let content = fs::read_to_string(path)?;
+    let now = chrono::Utc::now().timestamp();
+
+    for line in content.lines() {
+        // Browsers export HttpOnly cookies with a marker on the domain
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+            continue;
+        };
+        let expires: i64 = expires.trim().parse().unwrap_or(0);
+        if expires != 0 && expires < now {
+            continue;
+        }
+
+        let host = domain.trim_start_matches('.');
+        let secure = secure.eq_ignore_ascii_case("TRUE");
+        let mut cookie = format!("{}={}; Path={}", name, value, path);
+        if subdomains.eq_ignore_ascii_case("TRUE") {
+            cookie.push_str(&format!("; Domain={}", host));
+        }
+        if secure {
+            cookie.push_str("; Secure");
+        }
+
+        let scheme = if secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{}://{}{}", scheme, host, path))
+            .with_context(|| format!("Invalid cookie domain: {}", domain))?;
+        jar.add_cookie_str(&cookie, &url);
+    }
+
+    Ok(())
+}
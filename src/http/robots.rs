@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+/// The rules of a site's robots.txt that apply to this client, as described
+/// in RFC 9309.
+#[derive(Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    /// Requested interval between requests, a widely supported extension
+    pub crawl_delay: Option<Duration>,
+}
+
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Rules that disallow everything, for sites whose robots.txt couldn't
+    /// be fetched because of a server error.
+    pub fn disallow_all() -> Robots {
+        Robots {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parses a robots.txt, keeping the groups for `agent`, or else the
+    /// ones for `*`.
+    pub fn parse(content: &str, agent: &str) -> Robots {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agent_lines = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share one group
+                    if !in_agent_lines {
+                        groups.push(Group::default());
+                    }
+                    in_agent_lines = true;
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                    continue;
+                }
+                "allow" | "disallow" if !value.is_empty() => {
+                    if let Some(group) = groups.last_mut() {
+                        group.rules.push(Rule {
+                            allow: key.trim().eq_ignore_ascii_case("allow"),
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    let delay = value.parse().ok().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+                    if let (Some(group), Some(delay)) = (groups.last_mut(), delay) {
+                        group.crawl_delay = Some(delay);
+                    }
+                }
+                _ => {}
+            }
+            in_agent_lines = false;
+        }
+
+        let agent = agent.to_ascii_lowercase();
+        let matching = |name: &str| groups.iter().any(|group| group.agents.iter().any(|a| a == name));
+        let name = if matching(&agent) { agent.as_str() } else { "*" };
+
+        let mut robots = Robots::default();
+        for group in groups.into_iter().filter(|group| group.agents.iter().any(|a| a == name)) {
+            robots.rules.extend(group.rules);
+            robots.crawl_delay = robots.crawl_delay.max(group.crawl_delay);
+        }
+        robots
+    }
+
+    /// Whether a path (with its query) may be fetched: the longest matching
+    /// rule decides, and `Allow` wins a tie.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Matches a path against a rule, where `*` stands for any characters and
+/// a trailing `$` anchors the rule at the end of the path.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut pieces = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(pieces.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let pieces: Vec<&str> = pieces.collect();
+    let Some((last, middle)) = pieces.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for piece in middle {
+        match rest.find(piece) {
+            Some(index) => rest = &rest[index + piece.len()..],
+            None => return false,
+        }
+    }
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "\
+# Example
+User-agent: *
+Disallow: /private
+Allow: /private/public
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+User-agent: MyBot
+User-agent: otherbot
+Disallow: /
+Allow: /open
+";
+
+    #[test]
+    fn falls_back_to_the_star_group() {
+        let robots = Robots::parse(ROBOTS, "table-rustractor");
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/data"));
+        assert!(robots.allows("/private/public/data"));
+        assert_eq!(robots.crawl_delay, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn uses_the_group_naming_the_agent() {
+        for agent in ["mybot", "OtherBot"] {
+            let robots = Robots::parse(ROBOTS, agent);
+            assert!(!robots.allows("/private/public/data"));
+            assert!(robots.allows("/open/data"));
+            assert_eq!(robots.crawl_delay, None);
+        }
+    }
+
+    #[test]
+    fn matches_wildcards_and_anchors() {
+        let robots = Robots::parse(ROBOTS, "table-rustractor");
+        assert!(!robots.allows("/files/report.pdf"));
+        assert!(robots.allows("/files/report.pdf?download=1"));
+        assert!(matches("/a*b*c", "/a1b2c3"));
+        assert!(!matches("/a*b*c$", "/a1b2c3"));
+        assert!(matches("/a*$", "/a"));
+        assert!(!matches("/page$", "/page/2"));
+    }
+
+    #[test]
+    fn allow_wins_a_tie() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /page\nAllow: /page\n", "bot");
+        assert!(robots.allows("/page"));
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let robots = Robots::parse("User-agent: *\nDisallow:\n", "bot");
+        assert!(robots.allows("/anything"));
+        assert!(!Robots::disallow_all().allows("/anything"));
+    }
+}
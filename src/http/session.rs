@@ -0,0 +1,128 @@
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::HeaderMap;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::{Client, Purpose};
+
+/// Requests to run before fetching any document, typically to log in to a
+/// site. The cookies they set are kept for the rest of the run.
+///
+/// ```toml
+/// [[step]]
+/// url = "https://portal.example.com/login"
+/// csrf = "input[name=csrf_token]"
+///
+/// [step.fields]
+/// username = "me"
+/// password = "${PORTAL_PASSWORD}"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    #[serde(rename = "step")]
+    steps: Vec<Step>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Step {
+    /// Page fetched with GET, e.g. the login form; the form is posted here
+    /// too unless `submit` says otherwise
+    url: String,
+    /// URL the form is posted to, relative to `url`
+    submit: Option<String>,
+    /// Form fields to post; a `${NAME}` value is read from the environment
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+    /// Selector of the element holding a CSRF token on the page at `url`,
+    /// such as a hidden input or a `<meta name="csrf-token">`
+    csrf: Option<String>,
+    /// Field name for the CSRF token, if not the element's `name`
+    csrf_field: Option<String>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session> {
+        let content = fs::read_to_string(path)?;
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+impl Client {
+    /// Runs the steps of the `--session` file, if there is one. A step
+    /// without fields only fetches its page; one with fields posts them as
+    /// a form, after fetching the page first when a CSRF token is needed.
+    pub async fn start_session(&self) -> Result<()> {
+        let Some(session) = &self.session else {
+            return Ok(());
+        };
+
+        for (number, step) in session.steps.iter().enumerate() {
+            self.run_step(step)
+                .await
+                .with_context(|| format!("Session step {} ({}) failed", number + 1, step.url))?;
+        }
+        Ok(())
+    }
+
+    async fn run_step(&self, step: &Step) -> Result<()> {
+        let page = if step.fields.is_empty() || step.csrf.is_some() {
+            let response = self.send(&step.url, HeaderMap::new(), Purpose::Resource).await?.error_for_status()?;
+            Some(response.text().await?)
+        } else {
+            None
+        };
+        if step.fields.is_empty() {
+            return Ok(());
+        }
+
+        let mut fields = Vec::new();
+        for (name, value) in &step.fields {
+            fields.push((name.clone(), expand(value)?));
+        }
+        if let (Some(selector), Some(page)) = (&step.csrf, &page) {
+            fields.push(csrf_token(page, selector, step.csrf_field.as_deref())?);
+        }
+
+        let url = Url::parse(&step.url)?;
+        let target = match &step.submit {
+            Some(submit) => url.join(submit)?,
+            None => url,
+        };
+        self.add_cookies(target.as_str());
+        self.wait_for_turn(target.as_str()).await;
+        self.inner.post(target).form(&fields).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The name and value of the CSRF token on a page. The value comes from the
+/// element's `value` attribute, or `content` for meta tags.
+fn csrf_token(page: &str, selector: &str, field: Option<&str>) -> Result<(String, String)> {
+    let parsed = Selector::parse(selector).map_err(|err| anyhow!("Invalid CSRF selector '{}': {}", selector, err))?;
+    let document = Html::parse_document(page);
+    let Some(element) = document.select(&parsed).next() else {
+        bail!("No CSRF token matching '{}' on the page", selector);
+    };
+
+    let element = element.value();
+    let value = element.attr("value").or_else(|| element.attr("content")).unwrap_or_default();
+    let Some(name) = field.or_else(|| element.attr("name")) else {
+        bail!("The CSRF token element has no name; set csrf_field");
+    };
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Replaces a `${NAME}` value with the environment variable `NAME`, so
+/// passwords don't have to be written into the session file.
+fn expand(value: &str) -> Result<String> {
+    match value.strip_prefix("${").and_then(|value| value.strip_suffix('}')) {
+        Some(name) => std::env::var(name).with_context(|| format!("Environment variable {} is not set", name)),
+        None => Ok(value.to_string()),
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path};
+use zip::ZipArchive;
+
+use super::{decode, split_extension, Source, DOCUMENT_EXTENSIONS};
+
+/// Whether a path names a supported archive, judging by its extension.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy().to_ascii_lowercase();
+    [".zip", ".tar", ".tar.gz", ".tgz"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// Reads every HTML or Markdown file inside a ZIP or tar archive. Each
+/// document's output goes to a subdirectory mirroring its path inside the
+/// archive.
+pub fn read(path: &Path) -> Result<Vec<Source>> {
+    let name = path.to_string_lossy().to_ascii_lowercase();
+    let entries = if name.ends_with(".zip") {
+        read_zip(path)
+    } else {
+        let file = BufReader::new(File::open(path)?);
+        if name.ends_with(".tar") {
+            read_tar(file)
+        } else {
+            read_tar(MultiGzDecoder::new(file))
+        }
+    }
+    .with_context(|| format!("Failed to read archive: {}", path.display()))?;
+
+    Ok(entries
+        .into_iter()
+        .map(|(entry, html)| Source {
+            output_subdir: split_extension(Path::new(&entry)).0,
+            content: Some(Ok(html)),
+            ..Source::new(format!("{}!/{}", path.display(), entry))
+        })
+        .collect())
+}
+
+fn is_document_entry(name: &str) -> bool {
+    let (_, ext) = split_extension(Path::new(name));
+    DOCUMENT_EXTENSIONS.contains(&ext.as_str())
+}
+
+fn read_zip(path: &Path) -> Result<Vec<(String, String)>> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut entries = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let Some(name) = file.enclosed_name() else {
+            continue;
+        };
+        let name = name.to_string_lossy().into_owned();
+        if !file.is_file() || !is_document_entry(&name) {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        entries.push((name.clone(), decode(bytes, &name)?));
+    }
+
+    Ok(entries)
+}
+
+/// Normalises a tar entry path, dropping `.` components. Entries that
+/// could escape the output directory are skipped.
+fn entry_name(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn read_tar(reader: impl Read) -> Result<Vec<(String, String)>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut entries = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let Some(name) = entry_name(&entry.path()?) else {
+            continue;
+        };
+        if !entry.header().entry_type().is_file() || !is_document_entry(&name) {
+            continue;
+        }
+
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        entries.push((name.clone(), decode(bytes, &name)?));
+    }
+
+    Ok(entries)
+}
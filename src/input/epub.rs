@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use zip::ZipArchive;
+
+use super::{decode, split_extension, Source};
+
+type Book = ZipArchive<BufReader<File>>;
+
+/// Whether a path names an EPUB book, judging by its extension.
+pub fn is_epub(path: &Path) -> bool {
+    split_extension(path).1 == "epub"
+}
+
+/// Reads the XHTML chapters of an EPUB book in reading order. Each
+/// chapter's output goes to a subdirectory named after its file, relative
+/// to the package document.
+pub fn read(path: &Path) -> Result<Vec<Source>> {
+    read_chapters(path).with_context(|| format!("Failed to read EPUB book: {}", path.display()))
+}
+
+fn read_chapters(path: &Path) -> Result<Vec<Source>> {
+    let mut book = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+    // The container points to the package document, which lists the
+    // chapters in the manifest and their order in the spine
+    let container = read_text(&mut book, "META-INF/container.xml")?;
+    let container = roxmltree::Document::parse(&container)?;
+    let package_path = container
+        .descendants()
+        .find(|node| node.has_tag_name("rootfile"))
+        .and_then(|node| node.attribute("full-path"))
+        .context("No package document in META-INF/container.xml")?
+        .to_string();
+    let package_dir = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    let package = read_text(&mut book, &package_path)?;
+    let package = roxmltree::Document::parse(&package)
+        .with_context(|| format!("Failed to parse package document: {}", package_path))?;
+    let manifest: HashMap<&str, (&str, &str)> = package
+        .descendants()
+        .filter(|node| node.has_tag_name("item"))
+        .filter_map(|node| {
+            Some((
+                node.attribute("id")?,
+                (node.attribute("href")?, node.attribute("media-type").unwrap_or_default()),
+            ))
+        })
+        .collect();
+
+    let mut sources = Vec::new();
+    for itemref in package.descendants().filter(|node| node.has_tag_name("itemref")) {
+        let Some(&(href, media_type)) = itemref.attribute("idref").and_then(|id| manifest.get(id)) else {
+            continue;
+        };
+        if media_type != "application/xhtml+xml" && media_type != "text/html" {
+            continue;
+        }
+
+        let href = href.split('#').next().unwrap_or(href);
+        let entry = join(package_dir, href);
+        let bytes = read_bytes(&mut book, &entry)?;
+        let chapter = entry
+            .strip_prefix(package_dir)
+            .map_or(entry.as_str(), |name| name.trim_start_matches('/'));
+        sources.push(Source {
+            output_subdir: split_extension(Path::new(chapter)).0,
+            content: Some(Ok(decode(bytes, &entry)?)),
+            ..Source::new(format!("{}!/{}", path.display(), entry))
+        });
+    }
+
+    Ok(sources)
+}
+
+fn read_bytes(book: &mut Book, name: &str) -> Result<Vec<u8>> {
+    let mut file = book
+        .by_name(name)
+        .with_context(|| format!("Missing file in book: {}", name))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_text(book: &mut Book, name: &str) -> Result<String> {
+    decode(read_bytes(book, name)?, name)
+}
+
+/// Resolves an href relative to a directory inside the book.
+fn join(dir: &str, href: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use std::collections::HashMap;
+
+use crate::charset;
+
+/// A MIME entity: a whole message or one part of a multipart body.
+pub struct Part {
+    /// Lowercased media type, e.g. `text/html`
+    pub mime_type: String,
+    params: HashMap<String, String>,
+    encoding: String,
+    body: Vec<u8>,
+    pub parts: Vec<Part>,
+}
+
+impl Part {
+    pub fn parse(data: &[u8]) -> Result<Part> {
+        let (head, body) = split_head(data);
+        let headers = parse_headers(head);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+
+        let (mime_type, params) = parse_content_type(header("content-type").unwrap_or("text/plain"));
+        let encoding = header("content-transfer-encoding")
+            .unwrap_or("7bit")
+            .trim()
+            .to_ascii_lowercase();
+
+        let mut parts = Vec::new();
+        if mime_type.starts_with("multipart/") {
+            let boundary = params
+                .get("boundary")
+                .context("Multipart entity has no boundary")?;
+            for part in split_multipart(body, boundary) {
+                parts.push(Part::parse(part)?);
+            }
+        }
+
+        Ok(Part {
+            mime_type,
+            params,
+            encoding,
+            body: body.to_vec(),
+            parts,
+        })
+    }
+
+    /// The first part with the given media type, searching depth-first.
+    pub fn find(&self, mime_type: &str) -> Option<&Part> {
+        if self.mime_type == mime_type {
+            return Some(self);
+        }
+        self.parts.iter().find_map(|part| part.find(mime_type))
+    }
+
+    /// The body with its transfer encoding undone, decoded using the part's
+    /// charset.
+    pub fn text(&self) -> Result<String> {
+        let bytes = match self.encoding.as_str() {
+            "base64" => {
+                let encoded: Vec<u8> = self
+                    .body
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .context("Invalid base64 body")?
+            }
+            "quoted-printable" => decode_quoted_printable(&self.body),
+            _ => self.body.clone(),
+        };
+
+        Ok(charset::decode_html(&bytes, self.params.get("charset").map(String::as_str)))
+    }
+}
+
+/// Splits an entity at the first empty line into its header block and body.
+pub fn split_head(data: &[u8]) -> (&[u8], &[u8]) {
+    let mut start = 0;
+    for line in data.split_inclusive(|&b| b == b'\n') {
+        if line.trim_ascii().is_empty() {
+            return (&data[..start], &data[start + line.len()..]);
+        }
+        start += line.len();
+    }
+    (data, &[])
+}
+
+/// Parses header lines, joining folded continuation lines.
+pub fn parse_headers(head: &[u8]) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in String::from_utf8_lossy(head).lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// Splits a `Content-Type` value into the media type and its parameters.
+pub fn parse_content_type(value: &str) -> (String, HashMap<String, String>) {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    let mime_type = fields[0].trim().to_ascii_lowercase();
+    let params = fields[1..]
+        .iter()
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    (mime_type, params)
+}
+
+/// Returns the bodies between the boundary delimiter lines of a multipart
+/// entity, ignoring the preamble and epilogue.
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = Vec::new();
+    let mut part_start = None;
+    let mut start = 0;
+
+    for line in body.split_inclusive(|&b| b == b'\n') {
+        let end = start + line.len();
+        let rest = line.trim_ascii_end().strip_prefix(delimiter.as_bytes());
+        if let Some(rest @ (b"" | b"--")) = rest {
+            if let Some(part_start) = part_start {
+                parts.push(strip_line_ending(&body[part_start..start]));
+            }
+            if rest == b"--" {
+                return parts;
+            }
+            part_start = Some(end);
+        }
+        start = end;
+    }
+
+    // Tolerate a missing closing delimiter
+    if let Some(part_start) = part_start {
+        parts.push(&body[part_start..]);
+    }
+    parts
+}
+
+/// Drops the line break that belongs to the following delimiter.
+fn strip_line_ending(part: &[u8]) -> &[u8] {
+    let part = part.strip_suffix(b"\n").unwrap_or(part);
+    part.strip_suffix(b"\r").unwrap_or(part)
+}
+
+fn decode_quoted_printable(body: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(body.len());
+    let mut i = 0;
+    while i < body.len() {
+        if body[i] != b'=' {
+            decoded.push(body[i]);
+            i += 1;
+            continue;
+        }
+
+        let rest = &body[i + 1..];
+        if rest.starts_with(b"\r\n") {
+            i += 3;
+        } else if rest.starts_with(b"\n") {
+            i += 2;
+        } else if let Some(byte) = rest
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(b'=');
+            i += 1;
+        }
+    }
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_head_from_body() {
+        assert_eq!(split_head(b"A: 1\r\nB: 2\r\n\r\nbody\r\n"), (&b"A: 1\r\nB: 2\r\n"[..], &b"body\r\n"[..]));
+        assert_eq!(split_head(b"A: 1\n"), (&b"A: 1\n"[..], &b""[..]));
+    }
+
+    #[test]
+    fn joins_folded_headers() {
+        let headers = parse_headers(b"Subject: one\r\n\ttwo\r\nTo:  x@y \r\nnot a header\r\n");
+        assert_eq!(headers, [("Subject".to_string(), "one two".to_string()), ("To".to_string(), "x@y".to_string())]);
+    }
+
+    #[test]
+    fn reads_content_type_parameters() {
+        let (mime_type, params) = parse_content_type("Multipart/Related; Boundary=\"a;b\"; charset=UTF-8");
+        assert_eq!(mime_type, "multipart/related");
+        assert_eq!(params["boundary"], "a;b");
+        assert_eq!(params["charset"], "UTF-8");
+    }
+
+    #[test]
+    fn parses_multipart_entities() {
+        let message = b"Content-Type: multipart/related; boundary=\"XX\"\r\n\
+\r\n\
+preamble\r\n\
+--XX\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+plain\r\n\
+--XX\r\n\
+Content-Type: text/html; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+<p>caf=C3=A9 =3D soft=\r\n\
+break</p>\r\n\
+--XX--\r\n\
+epilogue\r\n";
+        let part = Part::parse(message).unwrap();
+        assert_eq!(part.parts.len(), 2);
+        assert_eq!(part.find("text/plain").unwrap().text().unwrap(), "plain");
+        assert_eq!(part.find("text/html").unwrap().text().unwrap(), "<p>café = softbreak</p>");
+        assert!(part.find("image/png").is_none());
+    }
+
+    #[test]
+    fn decodes_base64_bodies() {
+        let part = Part::parse(b"Content-Transfer-Encoding: base64\r\n\r\naGVs\r\nbG8=\r\n").unwrap();
+        assert_eq!(part.text().unwrap(), "hello");
+    }
+
+    #[test]
+    fn tolerates_a_missing_closing_delimiter() {
+        assert_eq!(split_multipart(b"--b\r\none\r\n--b\r\ntwo", "b"), [&b"one"[..], &b"two"[..]]);
+    }
+}
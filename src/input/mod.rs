@@ -0,0 +1,599 @@
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use clap::Args;
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::charset;
+use crate::clipboard;
+use crate::http::Client;
+use crate::output::slugify;
+
+mod archive;
+mod epub;
+mod mime;
+mod wayback;
+mod warc;
+
+/// Where to read documents from. Exactly one of the arguments in the
+/// `source` group has to be given.
+#[derive(Args)]
+pub struct InputArgs {
+    /// Input HTML file path, URL, `data:` URI, directory or glob pattern,
+    /// or `-` to read from stdin
+    #[arg(short, long, group = "source")]
+    pub input: Option<String>,
+
+    /// HTML snippet to extract tables from, given directly on the command line
+    #[arg(long, value_name = "HTML", group = "source")]
+    pub html: Option<String>,
+
+    /// File listing one input per line; blank lines and lines starting with
+    /// `#` are ignored
+    #[arg(long, group = "source")]
+    pub input_list: Option<PathBuf>,
+
+    /// URL of a sitemap or sitemap index whose pages should be processed
+    #[arg(long, group = "source")]
+    pub sitemap: Option<String>,
+
+    /// URL of an RSS or Atom feed whose linked articles should be processed
+    #[arg(long, group = "source")]
+    pub feed: Option<String>,
+
+    /// Read HTML copied from a browser from the clipboard
+    #[arg(long, group = "source")]
+    pub from_clipboard: bool,
+
+    /// Fetch URLs from the Internet Archive snapshot closest to this date
+    /// (`YYYY[-MM[-DD[Thh[:mm[:ss]]]]]`) instead of the live site
+    #[arg(long, value_name = "DATE", value_parser = wayback::parse_timestamp)]
+    pub wayback: Option<String>,
+
+    /// Only process listed or discovered URLs matching this regex
+    #[arg(long, global = true, value_parser = Regex::new)]
+    pub include_pattern: Option<Regex>,
+
+    /// Also extract the tables of documents embedded with `<iframe>`
+    #[arg(long, global = true)]
+    pub follow_iframes: bool,
+}
+
+/// File extensions picked up when walking a directory.
+const DOCUMENT_EXTENSIONS: &[&str] = &["html", "htm", "xhtml", "mhtml", "mht", "eml", "md", "markdown"];
+
+/// Extensions of compressed files, which are decompressed transparently.
+const COMPRESSED_EXTENSIONS: &[&str] = &["gz", "deflate", "zz"];
+
+/// A single document to extract tables from.
+#[derive(Debug)]
+pub struct Source {
+    /// File path, URL or `-` for stdin
+    pub location: String,
+    /// Directory for this document's output, relative to the output directory
+    pub output_subdir: PathBuf,
+    /// The document itself, or why it couldn't be read, when it was already
+    /// read while resolving the input (e.g. from an archive) rather than
+    /// needing to be fetched
+    pub content: Option<Result<String>>,
+    /// Timestamp of the Wayback Machine snapshot the document was fetched
+    /// from, if any
+    pub snapshot: Option<String>,
+    /// Where the document ended up after redirects, if it was redirected
+    pub final_url: Option<String>,
+    /// Whether the document is unchanged since it was last cached
+    pub unchanged: bool,
+    /// Whether the document was reached through another one (a link, an
+    /// iframe, a sitemap or a feed) rather than named as input, so that it
+    /// is fetched with GET instead of the configured method and body
+    pub linked: bool,
+}
+
+impl Source {
+    /// Whether the source is a local WARC file, which holds many documents.
+    pub fn is_warc(&self) -> bool {
+        let path = Path::new(&self.location);
+        self.content.is_none() && warc::is_warc(path) && path.is_file()
+    }
+
+    pub fn new(location: impl Into<String>) -> Self {
+        Source {
+            location: location.into(),
+            output_subdir: PathBuf::new(),
+            content: None,
+            snapshot: None,
+            final_url: None,
+            unchanged: false,
+            linked: false,
+        }
+    }
+}
+
+/// Collects the documents to process from the input arguments.
+pub async fn resolve(args: &InputArgs, client: &Client) -> Result<Vec<Source>> {
+    if let Some(input) = &args.input {
+        return resolve_input(input);
+    }
+    if let Some(html) = &args.html {
+        return Ok(vec![Source {
+            content: Some(Ok(html.clone())),
+            ..Source::new("inline")
+        }]);
+    }
+    if args.from_clipboard {
+        return Ok(vec![Source {
+            content: Some(Ok(clipboard::read_html()?)),
+            ..Source::new("clipboard")
+        }]);
+    }
+
+    let linked = args.sitemap.is_some() || args.feed.is_some();
+    let mut entries = if let Some(list) = &args.input_list {
+        let content = fs::read_to_string(list)
+            .with_context(|| format!("Failed to read input list: {}", list.display()))?;
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty() && !entry.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    } else if let Some(sitemap) = &args.sitemap {
+        sitemap_urls(client, sitemap).await?
+    } else if let Some(feed) = &args.feed {
+        feed_urls(client, feed).await?
+    } else {
+        Vec::new()
+    };
+
+    if let Some(pattern) = &args.include_pattern {
+        entries.retain(|entry| pattern.is_match(entry));
+    }
+    let mut sources = resolve_entries(&entries)?;
+    for source in &mut sources {
+        source.linked = linked;
+    }
+    Ok(sources)
+}
+
+/// The documents to process, in order. WARC files are expanded into the
+/// documents they hold only as they're reached, since they can be far
+/// larger than memory.
+pub fn documents(sources: Vec<Source>) -> impl Iterator<Item = Source> {
+    sources.into_iter().flat_map(|source| {
+        if source.is_warc() {
+            warc::documents(source)
+        } else {
+            Box::new(std::iter::once(source))
+        }
+    })
+}
+
+/// Resolves every entry of a list of inputs, giving each one its own output
+/// directory named after it.
+fn resolve_entries(entries: &[String]) -> Result<Vec<Source>> {
+    let mut sources = Vec::new();
+    let mut namer = SourceNamer::default();
+
+    for entry in entries {
+        let name = namer.name(entry);
+        for mut source in resolve_input(entry)? {
+            source.output_subdir = Path::new(&name).join(source.output_subdir);
+            sources.push(source);
+        }
+    }
+    Ok(sources)
+}
+
+/// Hands out unique output directory names for the entries of a batch.
+#[derive(Default)]
+pub struct SourceNamer {
+    used: HashSet<String>,
+}
+
+impl SourceNamer {
+    pub fn name(&mut self, entry: &str) -> String {
+        let base = entry_name(entry);
+        let mut name = base.clone();
+        let mut counter = 2;
+        while !self.used.insert(name.clone()) {
+            name = format!("{}-{}", base, counter);
+            counter += 1;
+        }
+        name
+    }
+
+    /// A source for a single document with its own output directory.
+    pub fn source(&mut self, location: &str) -> Source {
+        Source {
+            output_subdir: PathBuf::from(self.name(location)),
+            ..Source::new(location)
+        }
+    }
+}
+
+/// Page URLs listed in a sitemap, following nested sitemap indexes and
+/// leaving out pages disallowed by robots.txt.
+async fn sitemap_urls(client: &Client, sitemap: &str) -> Result<Vec<String>> {
+    let mut urls = Vec::new();
+    let mut pending = vec![sitemap.to_string()];
+    let mut visited = HashSet::new();
+
+    while let Some(location) = pending.pop() {
+        if !visited.insert(location.clone()) {
+            continue;
+        }
+
+        let xml = fetch_html(client, &location).await?;
+        let document = roxmltree::Document::parse(&xml)
+            .with_context(|| format!("Failed to parse sitemap: {}", location))?;
+        let is_index = document.root_element().has_tag_name("sitemapindex");
+
+        for loc in document.descendants().filter(|node| node.has_tag_name("loc")) {
+            let Some(url) = loc.text().map(str::trim).filter(|url| !url.is_empty()) else {
+                continue;
+            };
+            if is_index {
+                pending.push(url.to_string());
+            } else if client.allowed_by_robots(url).await {
+                urls.push(url.to_string());
+            } else {
+                eprintln!("Skipping {}: disallowed by robots.txt", url);
+            }
+        }
+    }
+
+    Ok(urls)
+}
+
+/// Article URLs linked from the items of an RSS feed or the entries of an
+/// Atom feed, resolved against the feed's own URL.
+async fn feed_urls(client: &Client, feed: &str) -> Result<Vec<String>> {
+    let xml = fetch_html(client, feed).await?;
+    let document = roxmltree::Document::parse(&xml)
+        .with_context(|| format!("Failed to parse feed: {}", feed))?;
+    let base = Url::parse(feed).ok();
+
+    let mut urls = Vec::new();
+    for entry in document
+        .descendants()
+        .filter(|node| node.has_tag_name("item") || node.has_tag_name("entry"))
+    {
+        let link = entry
+            .children()
+            .filter(|node| node.has_tag_name("link"))
+            .find_map(|link| match link.attribute("href") {
+                // Atom links are attributes; only the alternate one points
+                // to the article itself
+                Some(href) => matches!(link.attribute("rel"), None | Some("alternate")).then_some(href),
+                None => link.text(),
+            });
+        let Some(link) = link.map(str::trim).filter(|link| !link.is_empty()) else {
+            continue;
+        };
+
+        let url = base.as_ref().and_then(|base| base.join(link).ok());
+        urls.push(url.map_or_else(|| link.to_string(), String::from));
+    }
+
+    Ok(urls)
+}
+
+/// Output directory name for an entry of an input list: a slug of the URL
+/// without its scheme, or the file or directory name.
+fn entry_name(entry: &str) -> String {
+    let name = if is_url(entry) {
+        slugify(entry.split_once("://").map_or(entry, |(_, rest)| rest))
+    } else {
+        let path = Path::new(entry);
+        let stem = if path.is_dir() { path.to_path_buf() } else { split_extension(path).0 };
+        slugify(&stem.file_name().unwrap_or_default().to_string_lossy())
+    };
+
+    if name.is_empty() {
+        "input".to_string()
+    } else {
+        name
+    }
+}
+
+/// Expands an input value into the documents it refers to. Directories
+/// are walked recursively for HTML files, archives and EPUB books are read
+/// and glob patterns are expanded; in all cases each document's output goes
+/// to a subdirectory mirroring its path relative to the directory, the
+/// archive or book root or the pattern's fixed prefix. WARC files are only
+/// read later on, by `documents`.
+fn resolve_input(input: &str) -> Result<Vec<Source>> {
+    if is_url(input) || is_data_uri(input) || input == "-" {
+        return Ok(vec![Source::new(input)]);
+    }
+    if is_file_url(input) {
+        return resolve_input(&file_url_path(input)?.to_string_lossy());
+    }
+
+    let path = Path::new(input);
+    if path.is_dir() {
+        let pattern = path.join("**").join("*");
+        let files = expand_glob(&pattern.to_string_lossy())?
+            .into_iter()
+            .filter(|file| {
+                let (_, ext) = split_extension(file);
+                DOCUMENT_EXTENSIONS.contains(&ext.as_str())
+            });
+        return Ok(mirrored(path, files));
+    }
+
+    if epub::is_epub(path) && path.is_file() {
+        return epub::read(path);
+    }
+
+    if archive::is_archive(path) && path.is_file() {
+        return archive::read(path);
+    }
+
+    if input.contains(['*', '?', '[']) && !path.exists() {
+        let files = expand_glob(input)?;
+        if files.is_empty() {
+            bail!("No files match the pattern: {}", input);
+        }
+        return Ok(mirrored(&glob_base(path), files));
+    }
+
+    Ok(vec![Source::new(input)])
+}
+
+fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in glob::glob(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))? {
+        let file = entry?;
+        if file.is_file() {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+/// The leading components of a glob pattern that contain no wildcards.
+fn glob_base(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|component| match component {
+            Component::Normal(part) => !part.to_string_lossy().contains(['*', '?', '[']),
+            _ => true,
+        })
+        .collect()
+}
+
+fn mirrored(base: &Path, files: impl IntoIterator<Item = PathBuf>) -> Vec<Source> {
+    files
+        .into_iter()
+        .map(|file| {
+            let relative = file.strip_prefix(base).unwrap_or(&file);
+            Source {
+                output_subdir: split_extension(relative).0,
+                ..Source::new(file.to_string_lossy())
+            }
+        })
+        .collect()
+}
+
+/// Splits a file path into the path without its extension and the
+/// lowercase extension, looking through compression extensions so that
+/// `page.html.gz` gives `page` and `html`.
+fn split_extension(path: &Path) -> (PathBuf, String) {
+    let extension = |path: &Path| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default()
+    };
+
+    let mut stem = path.to_path_buf();
+    if COMPRESSED_EXTENSIONS.contains(&extension(&stem).as_str()) {
+        stem.set_extension("");
+    }
+    let ext = extension(&stem);
+    stem.set_extension("");
+    (stem, ext)
+}
+
+/// Reads a local file, decompressing and unpacking it as needed.
+fn read_file(path: &str) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read file: {}", path))?;
+    decode(bytes, path)
+}
+
+/// Decompresses gzip data (recognized by its magic bytes) and zlib/deflate
+/// data (recognized by the extension of `name`).
+fn decompress(bytes: Vec<u8>, name: &str) -> Result<Vec<u8>> {
+    let extension = Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+
+    let mut decoded = Vec::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        MultiGzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .with_context(|| format!("Failed to decompress gzip file: {}", name))?;
+    } else if matches!(extension.as_deref(), Some("deflate" | "zz")) {
+        ZlibDecoder::new(bytes.as_slice())
+            .read_to_end(&mut decoded)
+            .with_context(|| format!("Failed to decompress deflate file: {}", name))?;
+    } else {
+        return Ok(bytes);
+    }
+    Ok(decoded)
+}
+
+/// Decompresses a document and decodes it to text. MHTML files
+/// and saved emails, recognized by their extension, are unpacked to their
+/// first HTML part.
+fn decode(bytes: Vec<u8>, name: &str) -> Result<String> {
+    let bytes = decompress(bytes, name)?;
+
+    let (_, document_extension) = split_extension(Path::new(name));
+    if matches!(document_extension.as_str(), "mhtml" | "mht" | "eml") {
+        return mime::Part::parse(&bytes)?
+            .find("text/html")
+            .with_context(|| format!("No HTML part in MIME document: {}", name))?
+            .text()
+            .with_context(|| format!("Failed to decode MIME document: {}", name));
+    }
+
+    Ok(charset::decode_html(&bytes, None))
+}
+
+/// Whether a document is Markdown rather than HTML, judging by its extension.
+pub fn is_markdown(location: &str) -> bool {
+    let (_, ext) = split_extension(Path::new(location));
+    matches!(ext.as_str(), "md" | "markdown")
+}
+
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+fn is_file_url(source: &str) -> bool {
+    source.starts_with("file://")
+}
+
+/// The local path a `file://` URL points to, percent-decoded.
+fn file_url_path(url: &str) -> Result<PathBuf> {
+    Url::parse(url)
+        .ok()
+        .and_then(|parsed| parsed.to_file_path().ok())
+        .with_context(|| format!("Invalid file URL: {}", url))
+}
+
+fn is_data_uri(source: &str) -> bool {
+    source.starts_with("data:")
+}
+
+/// Decodes the payload of a `data:[<media type>][;base64],<data>` URI.
+fn decode_data_uri(uri: &str) -> Result<String> {
+    let (header, data) = uri["data:".len()..]
+        .split_once(',')
+        .context("Invalid data: URI, missing ','")?;
+
+    let bytes: Vec<u8> = percent_decode_str(data).collect();
+    let bytes = if header.ends_with(";base64") {
+        let encoded: Vec<u8> = bytes.into_iter().filter(|b| !b.is_ascii_whitespace()).collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .context("Invalid base64 in data: URI")?
+    } else {
+        bytes
+    };
+    Ok(charset::decode_html(&bytes, charset::content_type_charset(header)))
+}
+
+/// Returns the content of a document, fetching or reading it unless it was
+/// already loaded. With `--wayback`, URLs are swapped for their closest
+/// archived snapshot first.
+pub async fn load(source: &mut Source, args: &InputArgs, client: &Client) -> Result<String> {
+    if let Some(content) = source.content.take() {
+        return content;
+    }
+
+    let mut archived = false;
+    if let Some(timestamp) = args.wayback.as_deref().filter(|_| is_url(&source.location)) {
+        let snapshot = wayback::closest_snapshot(client, &source.location, timestamp).await?;
+        source.location = snapshot.url;
+        source.snapshot = Some(snapshot.timestamp);
+        archived = true;
+    }
+
+    if is_url(&source.location) {
+        let fetched = if archived {
+            client.fetch_archived(&source.location).await?
+        } else if source.linked {
+            client.fetch_linked(&source.location).await?
+        } else {
+            client.fetch(&source.location).await?
+        };
+        source.unchanged = fetched.unchanged;
+        source.final_url = (fetched.url != source.location).then_some(fetched.url);
+        return Ok(fetched.text);
+    }
+    fetch_html(client, &source.location).await
+}
+
+/// Loads the documents embedded in a page with `<iframe src>`, one level
+/// deep. Each gets an output directory named after its URL inside the
+/// page's own.
+pub async fn load_iframes(
+    source: &Source,
+    html: &str,
+    args: &InputArgs,
+    client: &Client,
+) -> Vec<(Source, Result<String>)> {
+    let base = source.final_url.as_deref().unwrap_or(&source.location);
+    let mut namer = SourceNamer::default();
+    let mut frames = Vec::new();
+
+    for location in iframe_locations(html, base) {
+        let mut frame = Source {
+            output_subdir: source.output_subdir.join(namer.name(&location)),
+            linked: true,
+            ..Source::new(location)
+        };
+        let html = load(&mut frame, args, client).await;
+        frames.push((frame, html));
+    }
+    frames
+}
+
+/// The `src` of every iframe in a document, resolved against the URL or
+/// path of the document.
+fn iframe_locations(html: &str, base: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("iframe[src]").unwrap();
+    let base_url = Url::parse(base).ok().filter(|_| is_url(base));
+    let base_dir = Path::new(base).is_file().then(|| Path::new(base).parent()).flatten();
+
+    let mut seen = HashSet::new();
+    document
+        .select(&selector)
+        .filter_map(|frame| frame.value().attr("src"))
+        .map(str::trim)
+        .filter_map(|src| {
+            if is_url(src) || is_data_uri(src) {
+                return Some(src.to_string());
+            }
+            if let Some(base) = &base_url {
+                let mut url = base.join(src).ok()?;
+                url.set_fragment(None);
+                return matches!(url.scheme(), "http" | "https").then(|| url.into());
+            }
+            // Relative paths in local files, but not `about:blank` and such
+            if Url::parse(src).is_ok() {
+                return None;
+            }
+            let path = base_dir?.join(src.split(['#', '?']).next()?);
+            Some(path.to_string_lossy().into_owned())
+        })
+        .filter(|location| seen.insert(location.clone()))
+        .collect()
+}
+
+pub async fn fetch_html(client: &Client, source: &str) -> Result<String> {
+    if source == "-" {
+        let mut html = Vec::new();
+        io::stdin().read_to_end(&mut html)
+            .context("Failed to read HTML from stdin")?;
+        Ok(charset::decode_html(&html, None))
+    } else if is_data_uri(source) {
+        decode_data_uri(source)
+    } else if is_file_url(source) {
+        read_file(&file_url_path(source)?.to_string_lossy())
+    } else if is_url(source) {
+        client.get_text(source).await
+    } else {
+        read_file(source)
+    }
+}
@@ -0,0 +1,328 @@
+use anyhow::{bail, Context, Result};
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::charset;
+use super::mime::{parse_content_type, parse_headers, split_head};
+use super::{split_extension, Source, SourceNamer};
+
+/// Whether a path names a WARC file, possibly compressed.
+pub fn is_warc(path: &Path) -> bool {
+    split_extension(path).1 == "warc"
+}
+
+/// The documents in the successful HTML response records of a WARC file,
+/// read one record at a time as they're reached. Tables are tagged with the
+/// record's target URL and each record gets an output directory named after
+/// it inside the file's own. A record that can't be decoded is skipped, but
+/// a file that can't be read or parsed any further ends with a failed
+/// document for the file itself.
+pub fn documents(file: Source) -> Box<dyn Iterator<Item = Source>> {
+    let mut records = match open(Path::new(&file.location)) {
+        Ok(reader) => Records::new(reader),
+        Err(err) => {
+            return Box::new(std::iter::once(Source {
+                content: Some(Err(err)),
+                ..file
+            }))
+        }
+    };
+    let mut namer = SourceNamer::default();
+    let mut file = Some(file);
+
+    Box::new(std::iter::from_fn(move || loop {
+        let record = match records.next_record() {
+            Ok(Some(record)) => record,
+            Ok(None) => return None,
+            Err(err) => {
+                return file.take().map(|file| Source {
+                    content: Some(Err(err.context("Failed to parse WARC file"))),
+                    ..file
+                });
+            }
+        };
+        let output_subdir = file.as_ref()?.output_subdir.join(namer.name(&record.uri));
+        match record.html() {
+            Some(Ok(html)) => {
+                return Some(Source {
+                    output_subdir,
+                    content: Some(Ok(html)),
+                    ..Source::new(record.uri)
+                })
+            }
+            Some(Err(err)) => eprintln!("Skipping WARC record for {}: {:#}", record.uri, err),
+            None => {}
+        }
+    }))
+}
+
+/// Opens a WARC file for reading, decompressing it on the fly.
+fn open(path: &Path) -> Result<Box<dyn BufRead>> {
+    let name = path.to_string_lossy();
+    let mut file = BufReader::new(File::open(path).with_context(|| format!("Failed to read file: {}", name))?);
+    let reader: Box<dyn Read> = if file.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(file))
+    } else if path.extension().is_some_and(|ext| ext == "deflate" || ext == "zz") {
+        Box::new(ZlibDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+struct Record {
+    uri: String,
+    /// The raw HTTP response, including status line and headers
+    block: Vec<u8>,
+}
+
+/// Reads the response records of a WARC file, in order, skipping over the
+/// blocks of all other records without keeping them.
+struct Records<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R) -> Self {
+        Records { reader }
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>> {
+        loop {
+            let Some(head) = self.read_head()? else {
+                return Ok(None);
+            };
+            let headers = parse_headers(&head);
+            let header = |name: &str| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.as_str())
+            };
+
+            let length: u64 = header("content-length")
+                .context("WARC record has no Content-Length")?
+                .parse()
+                .context("Invalid WARC Content-Length")?;
+            let uri = header("warc-target-uri").filter(|_| header("warc-type") == Some("response"));
+
+            let mut block = Vec::new();
+            let mut rest = (&mut self.reader).take(length);
+            let read = match uri {
+                Some(_) => rest.read_to_end(&mut block)? as u64,
+                None => io::copy(&mut rest, &mut io::sink())?,
+            };
+            if read < length {
+                bail!("Truncated WARC record");
+            }
+
+            if let Some(uri) = uri {
+                return Ok(Some(Record {
+                    uri: uri.trim_matches(['<', '>']).to_string(),
+                    block,
+                }));
+            }
+        }
+    }
+
+    /// The header lines of the next record, after the blank lines that end
+    /// the previous one, or `None` at the end of the file.
+    fn read_head(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim_ascii().is_empty() {
+                break;
+            }
+        }
+        if !line.starts_with(b"WARC/") {
+            bail!("Expected a WARC record header");
+        }
+
+        let mut head = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_until(b'\n', &mut line)? == 0 || line.trim_ascii().is_empty() {
+                return Ok(Some(head));
+            }
+            head.extend_from_slice(&line);
+        }
+    }
+}
+
+impl Record {
+    /// The decoded body of a successful HTML response, or `None` for any
+    /// other kind of response.
+    fn html(&self) -> Option<Result<String>> {
+        let (head, body) = split_head(&self.block);
+        let status = head
+            .split(|&b| b == b'\n')
+            .next()
+            .and_then(|line| std::str::from_utf8(line).ok())
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok());
+        if !status.is_some_and(|code| (200..300).contains(&code)) {
+            return None;
+        }
+
+        let headers = parse_headers(head);
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.to_ascii_lowercase())
+        };
+
+        let (mime_type, params) = parse_content_type(&header("content-type")?);
+        if mime_type != "text/html" && mime_type != "application/xhtml+xml" {
+            return None;
+        }
+
+        Some((|| {
+            let mut body = body.to_vec();
+            if header("transfer-encoding").is_some_and(|value| value.contains("chunked")) {
+                body = dechunk(&body)?;
+            }
+            body = match header("content-encoding").as_deref() {
+                Some("gzip" | "x-gzip") => read_all(MultiGzDecoder::new(body.as_slice()))?,
+                Some("deflate") => read_all(ZlibDecoder::new(body.as_slice()))?,
+                _ => body,
+            };
+
+            Ok(charset::decode_html(&body, params.get("charset").map(String::as_str)))
+        })())
+    }
+}
+
+fn read_all(mut reader: impl Read) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).context("Failed to decompress response")?;
+    Ok(bytes)
+}
+
+/// Undoes HTTP chunked transfer encoding.
+fn dechunk(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data
+            .iter()
+            .position(|&b| b == b'\n')
+            .context("Truncated chunked response")?;
+        let size = std::str::from_utf8(&data[..line_end])
+            .ok()
+            .and_then(|line| line.split(';').next())
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+            .context("Invalid chunk size")?;
+        data = &data[line_end + 1..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+        if size > data.len() {
+            bail!("Truncated chunked response");
+        }
+        body.extend_from_slice(&data[..size]);
+        data = data[size..].trim_ascii_start();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn record(warc_type: &str, uri: &str, block: &[u8]) -> Vec<u8> {
+        let mut record = format!(
+            "WARC/1.0\r\nWARC-Type: {}\r\nWARC-Target-URI: <{}>\r\nContent-Length: {}\r\n\r\n",
+            warc_type,
+            uri,
+            block.len()
+        )
+        .into_bytes();
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+        record
+    }
+
+    fn read_all_records(data: &[u8]) -> Result<Vec<Record>> {
+        let mut records = Records::new(data);
+        let mut all = Vec::new();
+        while let Some(record) = records.next_record()? {
+            all.push(record);
+        }
+        Ok(all)
+    }
+
+    #[test]
+    fn reads_response_records_only() {
+        let data = [
+            record("warcinfo", "", b"software: test\r\n"),
+            record("request", "http://a/", b"GET / HTTP/1.1\r\n\r\n"),
+            record("response", "http://a/", b"HTTP/1.1 200 OK\r\n\r\none"),
+            record("response", "http://b/", b"HTTP/1.1 200 OK\r\n\r\ntwo\r\n\r\n"),
+        ]
+        .concat();
+        let records = read_all_records(&data).unwrap();
+        let uris: Vec<&str> = records.iter().map(|record| record.uri.as_str()).collect();
+        assert_eq!(uris, ["http://a/", "http://b/"]);
+        assert_eq!(records[1].block, b"HTTP/1.1 200 OK\r\n\r\ntwo\r\n\r\n");
+    }
+
+    #[test]
+    fn rejects_broken_framing() {
+        let data = record("response", "http://a/", b"HTTP/1.1 200 OK\r\n\r\nbody");
+        let error = read_all_records(&data[..data.len() - 8]).err().unwrap();
+        assert_eq!(error.to_string(), "Truncated WARC record");
+
+        let error = read_all_records(b"garbage\r\n").err().unwrap();
+        assert_eq!(error.to_string(), "Expected a WARC record header");
+
+        let error = read_all_records(b"WARC/1.0\r\nWARC-Type: response\r\n\r\n").err().unwrap();
+        assert_eq!(error.to_string(), "WARC record has no Content-Length");
+    }
+
+    #[test]
+    fn decodes_html_responses() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all("<p>café</p>".as_bytes()).unwrap();
+        let body = gzip.finish().unwrap();
+        let mut block = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Encoding: gzip\r\n\
+             Transfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+            body.len()
+        )
+        .into_bytes();
+        block.extend_from_slice(&body);
+        block.extend_from_slice(b"\r\n0\r\n\r\n");
+
+        let record = Record {
+            uri: "http://a/".to_string(),
+            block,
+        };
+        assert_eq!(record.html().unwrap().unwrap(), "<p>café</p>");
+    }
+
+    #[test]
+    fn ignores_other_responses() {
+        let response = |block: &[u8]| Record {
+            uri: "http://a/".to_string(),
+            block: block.to_vec(),
+        };
+        assert!(response(b"HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\n\r\n").html().is_none());
+        assert!(response(b"HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n").html().is_none());
+        assert!(response(b"HTTP/1.1 200 OK\r\n\r\n<p>").html().is_none());
+    }
+
+    #[test]
+    fn undoes_chunked_encoding() {
+        assert_eq!(dechunk(b"3\r\nabc\r\n2;ext=1\r\nde\r\n0\r\n\r\n").unwrap(), b"abcde");
+        assert!(dechunk(b"5\r\nab").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::http::Client;
+
+const AVAILABILITY_API: &str = "https://archive.org/wayback/available";
+
+/// An archived copy of a page in the Wayback Machine.
+pub struct Snapshot {
+    /// URL of the unmodified archived page, without the Wayback toolbar
+    pub url: String,
+    /// Capture time as `YYYYMMDDhhmmss`
+    pub timestamp: String,
+}
+
+#[derive(Deserialize)]
+struct Availability {
+    archived_snapshots: ArchivedSnapshots,
+}
+
+#[derive(Deserialize)]
+struct ArchivedSnapshots {
+    closest: Option<Closest>,
+}
+
+#[derive(Deserialize)]
+struct Closest {
+    available: bool,
+    url: String,
+    timestamp: String,
+}
+
+/// Parses a `--wayback` date into the digits-only timestamp format used by
+/// the Wayback Machine, e.g. `2021-03-04` becomes `20210304`.
+pub fn parse_timestamp(value: &str) -> Result<String, String> {
+    let timestamp: String = value
+        .chars()
+        .filter(|c| !matches!(c, '-' | ':' | 'T' | ' '))
+        .collect();
+    if timestamp.len() < 4 || timestamp.len() > 14 || !timestamp.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("invalid date '{}', expected e.g. 2021-03-04", value));
+    }
+    Ok(timestamp)
+}
+
+/// Looks up the snapshot of a URL closest to the given timestamp using the
+/// availability API.
+pub async fn closest_snapshot(client: &Client, url: &str, timestamp: &str) -> Result<Snapshot> {
+    let api = Url::parse_with_params(AVAILABILITY_API, [("url", url), ("timestamp", timestamp)])?;
+    let response = client
+        .get_without_auth(api.as_str())
+        .await?
+        .error_for_status()
+        .context("Wayback Machine availability lookup failed")?
+        .text()
+        .await?;
+    let availability: Availability =
+        serde_json::from_str(&response).context("Unexpected Wayback Machine availability response")?;
+
+    let Some(closest) = availability.archived_snapshots.closest.filter(|closest| closest.available) else {
+        bail!("No Wayback Machine snapshot of {}", url);
+    };
+
+    // The `id_` flag asks for the page as originally captured, without the
+    // rewritten links and banner
+    let prefix = format!("/web/{}/", closest.timestamp);
+    let raw = format!("/web/{}id_/", closest.timestamp);
+    Ok(Snapshot {
+        url: closest.url.replacen(&prefix, &raw, 1).replacen("http://", "https://", 1),
+        timestamp: closest.timestamp,
+    })
+}
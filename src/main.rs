@@ -1,173 +1,808 @@
-use clap::Parser;
-use csv::Writer;
-use reqwest;
+use clap::{ArgGroup, Parser, Subcommand};
+use serde::Serialize;
 use scraper::{Html, Selector, ElementRef};
-use std::fs;
-use std::path::PathBuf;
-use anyhow::{Result, Context};
+use std::collections::HashSet;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use ego_tree::NodeId;
+
+mod charset;
+mod clipboard;
+mod crawl;
+mod extract;
+mod http;
+mod input;
+mod markdown;
+mod output;
+mod paginate;
+mod schema;
+mod select;
+mod transform;
+
+use futures::stream::{self, StreamExt};
+use crawl::CrawlArgs;
+use extract::{ExtractArgs, LinkMode, Ragged, Tfoot};
+use http::HttpArgs;
+use input::{InputArgs, Source};
+use output::OutputArgs;
+use paginate::PaginationArgs;
+use select::SelectionArgs;
+use transform::TransformArgs;
 
 #[derive(Parser)]
 #[command(author, version, about = "Extract tables from HTML files and save them as CSV", long_about = None)]
+#[command(group(ArgGroup::new("source").required(true)))]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
-    /// Input HTML file path or URL
-    #[arg(short, long)]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    http: HttpArgs,
+
+    #[command(flatten)]
+    pagination: PaginationArgs,
 
-    /// Output directory for CSV files
-    #[arg(short, long, default_value = ".")]
-    output_dir: PathBuf,
+    /// Number of documents to fetch at the same time
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+
+    #[command(flatten)]
+    extract: ExtractArgs,
+
+    #[command(flatten)]
+    selection: SelectionArgs,
+
+    #[command(flatten)]
+    transform: TransformArgs,
+
+    #[command(flatten)]
+    output: OutputArgs,
 }
 
-#[derive(Clone,Debug)]
-struct Cell {
-    content: String,
-    colspan: usize,
-    rowspan: usize,
+#[derive(Subcommand)]
+enum Command {
+    /// Follow links from a seed URL and extract tables from every page
+    Crawl(CrawlArgs),
 }
 
-async fn fetch_html(source: &str) -> Result<String> {
-    if source.starts_with("http://") || source.starts_with("https://") {
-        Ok(reqwest::get(source)
-            .await?
-            .text()
-            .await?)
-    } else {
-        Ok(fs::read_to_string(source)
-            .with_context(|| format!("Failed to read file: {}", source))?)
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    /// Input file path or URL the table was extracted from
+    pub source: String,
+    /// 1-based position of the table in its source document
+    pub index: usize,
+    pub caption: Option<String>,
+    /// Text of the nearest heading before the table
+    pub heading: Option<String>,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    /// Part of the source table this one holds when it was split up, such
+    /// as `totals`
+    pub part: Option<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Number of rows at the top of `rows` that hold column headers
+    pub header_rows: usize,
+    /// Number of rows at the bottom of `rows` from a `<tfoot>`, such as
+    /// totals, which stay there when the data rows are reordered
+    pub footer_rows: usize,
+    /// Geometry of the merged cells, in grid coordinates
+    pub spans: Vec<Span>,
+    /// Timestamp of the Wayback Machine snapshot the source was fetched from
+    pub snapshot: Option<String>,
+    /// URL the source was redirected to, if any
+    pub final_url: Option<String>,
+}
+
+/// A cell spanning more than one row or column, anchored at its top-left
+/// grid position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub row: usize,
+    pub col: usize,
+    pub rowspan: usize,
+    pub colspan: usize,
+}
+
+impl Table {
+    /// Human-readable name for the table: its caption, or else its `id`.
+    pub fn label(&self) -> Option<&str> {
+        self.caption.as_deref().or(self.id.as_deref())
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Rows holding the column headers: those marked up as headers, or the
+    /// first row of a table without any.
+    pub fn header(&self) -> &[Vec<String>] {
+        &self.rows[..self.header_rows.max(1).min(self.rows.len())]
+    }
+
+    pub fn data_rows(&self) -> &[Vec<String>] {
+        &self.rows[self.header().len()..]
+    }
+
+    /// Column names taken from the last header row, with blanks replaced by
+    /// `column_N` and duplicates disambiguated with a numeric suffix.
+    pub fn column_names(&self) -> Vec<String> {
+        let header = self.header().last().map(Vec::as_slice).unwrap_or_default();
+        let mut seen = std::collections::HashSet::new();
+
+        (0..self.width())
+            .map(|i| {
+                let base = header
+                    .get(i)
+                    .map(|name| name.trim())
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("column_{}", i + 1));
+                let mut name = base.clone();
+                let mut counter = 2;
+                while !seen.insert(name.clone()) {
+                    name = format!("{}_{}", base, counter);
+                    counter += 1;
+                }
+                name
+            })
+            .collect()
     }
 }
 
+/// Elements always extracted as tables: HTML tables, and the `<div>`-based
+/// tables of JavaScript frameworks marked up with ARIA roles.
+pub const TABLE_ELEMENTS: &str = "table, [role=table], [role=grid], [role=treegrid]";
+
+/// Rows and cells of ARIA tables.
+const ARIA_ROWS: &str = "[role=row]";
+const ARIA_CELLS: &str = "[role=cell], [role=gridcell], [role=columnheader], [role=rowheader]";
+
 fn get_cell_spans(cell: ElementRef) -> (usize, usize) {
-    let colspan = cell.value().attr("colspan")
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(1);
-    let rowspan = cell.value().attr("rowspan")
-        .and_then(|v| v.parse().ok())
-        .unwrap_or(1);
+    let attr = |name: &str| {
+        cell.value().attr(name).or_else(|| cell.value().attr(&format!("aria-{}", name)))
+    };
+    // Limits from the HTML spec, which also protect against absurd values
+    let colspan = attr("colspan")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(1)
+        .clamp(1, 1000);
+    let rowspan = attr("rowspan")
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(1)
+        .clamp(1, 65534);
     (colspan, rowspan)
 }
 
-fn extract_tables(html: &str) -> Result<Vec<Vec<Vec<String>>>> {
+fn get_caption(table: ElementRef) -> Option<String> {
+    table.children()
+        .filter_map(ElementRef::wrap)
+        .find(|child| child.value().name() == "caption")
+        .map(|caption| caption.text().collect::<String>().trim().to_string())
+        .filter(|caption| !caption.is_empty())
+}
+
+/// The accessible name of an ARIA table, from `aria-label` or the element
+/// `aria-labelledby` points to.
+fn get_aria_label(table: ElementRef, document: &Html) -> Option<String> {
+    let label = match table.value().attr("aria-labelledby") {
+        Some(ids) => ids
+            .split_whitespace()
+            .filter_map(|id| Selector::parse(&format!("[id=\"{}\"]", id)).ok())
+            .filter_map(|selector| document.select(&selector).next())
+            .map(|element| element.text().collect::<String>())
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => table.value().attr("aria-label")?.to_string(),
+    };
+    let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!label.is_empty()).then_some(label)
+}
+
+/// The nearest `h1`-`h6` before an element in document order, whether it
+/// is a preceding sibling, inside one, or before one of the ancestors.
+fn preceding_heading(element: ElementRef) -> Option<String> {
+    let is_heading = |element: &ElementRef| {
+        matches!(element.value().name(), "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+    };
+
+    std::iter::once(*element)
+        .chain(element.ancestors())
+        .flat_map(|node| node.prev_siblings())
+        .filter_map(ElementRef::wrap)
+        .find_map(|sibling| {
+            if is_heading(&sibling) {
+                Some(sibling)
+            } else {
+                sibling.descendants().filter_map(ElementRef::wrap).filter(is_heading).last()
+            }
+        })
+        .map(|heading| heading.text().collect::<String>().split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|heading| !heading.is_empty())
+}
+
+/// Extracts the tables of an HTML document that the selection options let
+/// through. Tables keep their position among all tables of the document
+/// either way. Links are resolved against `url`, or the document's `<base>`.
+fn extract_tables(
+    html: &str,
+    source: &str,
+    url: Option<&Url>,
+    extract: &ExtractArgs,
+    selection: &SelectionArgs,
+) -> Result<Vec<Table>> {
     let document = Html::parse_document(html);
-    let table_selector = Selector::parse("table").unwrap();
-    let row_selector = Selector::parse("tr").unwrap();
-    let cell_selector = Selector::parse("td, th").unwrap();
+    let base_href = document
+        .select(&Selector::parse("base[href]").unwrap())
+        .next()
+        .and_then(|base| base.value().attr("href"));
+    let base = match (url, base_href) {
+        (Some(url), Some(href)) => url.join(href).ok(),
+        (None, Some(href)) => Url::parse(href).ok(),
+        (url, None) => url.cloned(),
+    };
+    let base = base.as_ref();
+    let table_selector = Selector::parse(&extract.table_selector()).unwrap();
+    let html_rows = Selector::parse("tr").unwrap();
+    let html_cells = Selector::parse("td, th").unwrap();
+    let aria_rows = Selector::parse(ARIA_ROWS).unwrap();
+    let aria_cells = Selector::parse(ARIA_CELLS).unwrap();
 
+    let filter = selection.element_filter(&document, &table_selector);
     let mut tables = Vec::new();
 
-    for table in document.select(&table_selector) {
-        let mut grid: Vec<Vec<Option<Cell>>> = Vec::new();
-        let mut max_columns = 0;
-
-        // First pass: collect all rows and determine the table dimensions
-        for row in table.select(&row_selector) {
-            let mut current_row: Vec<Option<Cell>> = Vec::new();
-            let mut col_index = 0;
-
-            // Fill in any cells from previous rows' rowspans
-            while col_index < max_columns && grid.last().map_or(false, |last_row| {
-                last_row.get(col_index).map_or(false, |cell| {
-                    cell.as_ref().map_or(false, |c| c.rowspan > 1)
-                })
-            }) {
-                if let Some(prev_cell) = &grid.last().unwrap()[col_index] {
-                    current_row.push(Some(Cell {
-                        content: prev_cell.content.clone(),
-                        colspan: prev_cell.colspan,
-                        rowspan: prev_cell.rowspan - 1,
-                    }));
-                    col_index += prev_cell.colspan;
-                }
-            }
+    let elements = document.select(&table_selector).filter(|element| extract.keeps(*element));
+    for (table_index, table) in elements.enumerate() {
+        if !filter.allows(table) {
+            continue;
+        }
 
-            // Process cells in the current row
-            for cell in row.select(&cell_selector) {
-                // Skip columns that are already filled by a previous colspan
-                while col_index < current_row.len() && current_row[col_index].is_some() {
-                    col_index += 1;
-                }
+        let parts = match table.value().name() {
+            "table" => read_grid(table, &html_rows, &html_cells, extract, base),
+            "dl" => Ok(vec![Table {
+                rows: extract.definition_rows(table, base),
+                header_rows: 1,
+                ..Table::default()
+            }]),
+            "ul" | "ol" => Ok(vec![Table {
+                rows: extract.list_rows(table, base),
+                header_rows: 1,
+                ..Table::default()
+            }]),
+            _ => read_grid(table, &aria_rows, &aria_cells, extract, base),
+        }
+        .with_context(|| format!("Table {} is inconsistent", table_index + 1))?;
 
-                let (colspan, rowspan) = get_cell_spans(cell);
-                let content = cell.text().collect::<String>().trim().to_string();
+        for content in parts.into_iter().filter(|content| !content.rows.is_empty()) {
+            let mut content = Table {
+                source: source.to_string(),
+                index: table_index + 1,
+                caption: get_caption(table).or_else(|| get_aria_label(table, &document)),
+                heading: preceding_heading(table),
+                id: table.value().attr("id")
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty()),
+                classes: table.value().classes().map(str::to_string).collect(),
+                ..content
+            };
+            extract
+                .limit_cell_length(&mut content)
+                .with_context(|| format!("Table {} has an overlong cell", table_index + 1))?;
+            tables.push(content);
+        }
+    }
 
-                let new_cell = Cell {
-                    content,
-                    colspan,
+    Ok(tables)
+}
+
+/// Lays out the cells of a table's rows on a grid, repeating cells that
+/// span several rows or columns, and pads every row to the table width.
+/// Only the rows, merged cells and header rows of the result are set.
+/// `<tfoot>` rows come last, or in a second `totals` table with the same
+/// header rows for `--tfoot separate`, and row groups are split up or
+/// labeled as requested.
+fn read_grid(
+    table: ElementRef,
+    row_selector: &Selector,
+    cell_selector: &Selector,
+    extract: &ExtractArgs,
+    base: Option<&Url>,
+) -> Result<Vec<Table>> {
+    let mut grid: Vec<Vec<Option<String>>> = Vec::new();
+    // Link targets of every cell, for `--links extra-column`
+    let mut links: Vec<Vec<String>> = Vec::new();
+    let mut spans = Vec::new();
+    let mut header_rows = 0;
+    // Number of columns up to the last cell of each row
+    let mut widths = Vec::new();
+    // Row group of each row, and whether the row heads a group
+    let mut sections = Vec::new();
+    let grouped = extract.split_by_tbody || extract.group_column;
+
+    // Cells from previous rows that still cover a column, together with
+    // the number of rows they have left to cover
+    let mut carried: Vec<Option<(String, usize)>> = Vec::new();
+
+    let (body, footer): (Vec<_>, Vec<_>) = table
+        .select(row_selector)
+        .filter(|row| !extract.skips(*row, table))
+        .partition(|row| row_group(*row) != Some("tfoot"));
+    let has_thead = body.iter().any(|row| row_group(*row) == Some("thead"));
+    let footer_rows = match extract.tfoot {
+        Tfoot::Skip => 0,
+        Tfoot::Include | Tfoot::Separate => footer.len(),
+    };
+
+    for row in body.into_iter().chain(footer.into_iter().take(footer_rows)) {
+        let mut current_row: Vec<Option<String>> = Vec::new();
+        let mut row_links: Vec<String> = Vec::new();
+
+        for cell in row.select(cell_selector).filter(|cell| !extract.skips(*cell, row)) {
+            // Skip columns that are already filled by a previous rowspan
+            fill_rowspans(&mut current_row, &mut carried);
+
+            let (colspan, rowspan) = get_cell_spans(cell);
+            let content = extract.cell_text(cell, base);
+
+            let col_index = current_row.len();
+            if extract.links == Some(LinkMode::ExtraColumn) {
+                row_links.resize(col_index + 1, String::new());
+                row_links[col_index] = extract.cell_links(cell, base).join(" ");
+            }
+            if colspan > 1 || rowspan > 1 {
+                spans.push(Span {
+                    row: grid.len(),
+                    col: col_index,
                     rowspan,
-                };
-
-                // Fill in all columns this cell spans
-                for _ in 0..colspan {
-                    if col_index >= current_row.len() {
-                        current_row.push(Some(new_cell.clone()));
-                    } else {
-                        current_row[col_index] = Some(new_cell.clone());
-                    }
-                    col_index += 1;
+                    colspan,
+                });
+            }
+
+            // Fill in all columns this cell spans
+            if carried.len() < col_index + colspan {
+                carried.resize(col_index + colspan, None);
+            }
+            for carried_cell in &mut carried[col_index..col_index + colspan] {
+                current_row.push(Some(content.clone()));
+                if rowspan > 1 {
+                    *carried_cell = Some((content.clone(), rowspan - 1));
                 }
             }
+        }
 
-            max_columns = max_columns.max(col_index);
-            
-            // Pad the row to max_columns with None
-            while current_row.len() < max_columns {
+        // Fill in rowspans that extend past the last cell of this row
+        while current_row.len() < carried.len() {
+            if carried[current_row.len()].is_some() {
+                fill_rowspans(&mut current_row, &mut carried);
+            } else {
                 current_row.push(None);
             }
+        }
 
-            grid.push(current_row);
+        let heading = grouped && is_group_heading(row, cell_selector);
+        if header_rows == grid.len() && !heading && is_header_row(row, cell_selector, has_thead) {
+            header_rows += 1;
         }
+        sections.push((row_group_element(row).map(|group| group.id()), heading));
+        widths.push(current_row.iter().rposition(Option::is_some).map_or(0, |last| last + 1));
+        grid.push(current_row);
+        links.push(row_links);
+    }
 
-        // Convert grid to final table format
-        let mut final_table = Vec::new();
-        for row in grid {
-            let row_data: Vec<String> = row.into_iter()
-                .map(|cell| cell.map_or(String::new(), |c| c.content))
-                .collect();
-            final_table.push(row_data);
+    let max_columns = widths.iter().copied().max().unwrap_or(0);
+    let width = match extract.ragged {
+        Ragged::Pad => max_columns,
+        Ragged::Truncate => widths.first().copied().unwrap_or(0),
+        Ragged::Error => {
+            let min_columns = widths.iter().copied().min().unwrap_or(0);
+            if min_columns != max_columns {
+                bail!("Rows have between {} and {} cells", min_columns, max_columns);
+            }
+            if let Some(span) = spans.iter().find(|span| span.row + span.rowspan > grid.len()) {
+                bail!("A cell in row {} spans past the last row", span.row + 1);
+            }
+            max_columns
         }
+    };
+
+    // Rowspans can't extend past the last row of the table, nor colspans
+    // past its last column
+    spans.retain(|span| span.col < width);
+    for span in &mut spans {
+        span.rowspan = span.rowspan.min(grid.len() - span.row);
+        span.colspan = span.colspan.min(width - span.col);
+    }
+
+    // Convert grid to final table format, making every row as wide as the
+    // table
+    let mut final_table = Vec::new();
+    for row in grid {
+        let mut row_data: Vec<String> = row.into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        row_data.resize(width, String::new());
+        final_table.push(row_data);
+    }
 
-        if !final_table.is_empty() {
-            tables.push(final_table);
+    let mut content = Table {
+        rows: final_table,
+        header_rows,
+        footer_rows,
+        spans,
+        ..Table::default()
+    };
+    if extract.links == Some(LinkMode::ExtraColumn) {
+        links.iter_mut().for_each(|row| row.truncate(width));
+        transform::insert_companion_columns(&mut content, links, "url");
+    }
+
+    let footer_start = content.rows.len() - footer_rows;
+    let mut parts = vec![content];
+    if extract.tfoot == Tfoot::Separate && footer_rows > 0 && footer_start > 0 {
+        let header_len = parts[0].header().len().min(footer_start);
+        let rows = 0..parts[0].rows.len();
+        let mut totals = parts[0].clone();
+        totals.part = Some("totals".to_string());
+        transform::retain_rows(&mut totals, &rows.clone().map(|r| r < header_len || r >= footer_start).collect::<Vec<_>>());
+        transform::retain_rows(&mut parts[0], &rows.map(|r| r < footer_start).collect::<Vec<_>>());
+        sections.truncate(footer_start);
+        parts.push(totals);
+    }
+
+    if extract.split_by_tbody {
+        let sectioned = split_sections(parts.remove(0), &sections);
+        parts.splice(0..0, sectioned);
+    } else if extract.group_column {
+        add_group_column(&mut parts[0], &sections);
+    }
+    Ok(parts)
+}
+
+/// Splits a table into one table per row group (`<tbody>`), each with the
+/// table's header rows. A group starting with a group heading row is named
+/// after it, and otherwise after its position.
+fn split_sections(table: Table, sections: &[(Option<NodeId>, bool)]) -> Vec<Table> {
+    let header_len = table.header().len();
+    let mut groups: Vec<(Option<NodeId>, Vec<bool>, Option<String>)> = Vec::new();
+
+    for (r, &(group, heading)) in sections.iter().enumerate().skip(header_len) {
+        if groups.last().is_none_or(|(last, _, _)| *last != group) {
+            let mut keep = vec![false; table.rows.len()];
+            keep[..header_len].fill(true);
+            let name = heading.then(|| group_name(&table.rows[r]));
+            groups.push((group, keep, name));
+            if heading {
+                continue;
+            }
         }
+        if let Some((_, keep, _)) = groups.last_mut() {
+            keep[r] = true;
+        }
+    }
+    if groups.len() < 2 {
+        return vec![table];
     }
 
-    Ok(tables)
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(n, (_, keep, name))| {
+            let mut section = table.clone();
+            transform::retain_rows(&mut section, &keep);
+            section.part = Some(name.filter(|name| !name.is_empty()).unwrap_or_else(|| format!("section {}", n + 1)));
+            section
+        })
+        .collect()
+}
+
+/// Adds a first `group` column holding the text of the group heading row
+/// each data row follows within its row group, and removes those rows.
+fn add_group_column(table: &mut Table, sections: &[(Option<NodeId>, bool)]) {
+    let header_len = table.header().len();
+    let mut keep = vec![true; table.rows.len()];
+    let mut values = vec![String::new(); table.rows.len()];
+    let mut current: (Option<NodeId>, String) = (None, String::new());
+
+    for (r, &(group, heading)) in sections.iter().enumerate().skip(header_len) {
+        if group != current.0 {
+            current = (group, String::new());
+        }
+        if heading {
+            current.1 = group_name(&table.rows[r]);
+            keep[r] = false;
+        }
+        values[r].clone_from(&current.1);
+    }
+    transform::insert_first_column(table, "group", values);
+    transform::retain_rows(table, &keep);
+}
+
+/// Name of a group from its heading row: the first non-empty cell.
+fn group_name(row: &[String]) -> String {
+    row.iter().map(|cell| cell.trim()).find(|cell| !cell.is_empty()).unwrap_or_default().to_string()
+}
+
+/// Whether a row heads a group of rows, like `<tr><th colspan="4">Europe</th></tr>`:
+/// it has a single cell, which is a header cell or spans several columns.
+fn is_group_heading(row: ElementRef, cell_selector: &Selector) -> bool {
+    let mut cells = row.select(cell_selector);
+    match (cells.next(), cells.next()) {
+        (Some(cell), None) => cell.value().name() == "th" || get_cell_spans(cell).0 > 1,
+        _ => false,
+    }
+}
+
+/// The nearest row group (`thead`, `tbody` or `tfoot`) a row belongs to, or
+/// the table for rows directly in it.
+fn row_group_element(row: ElementRef<'_>) -> Option<ElementRef<'_>> {
+    row.ancestors()
+        .filter_map(ElementRef::wrap)
+        .find(|ancestor| matches!(ancestor.value().name(), "thead" | "tbody" | "tfoot" | "table"))
+}
+
+fn row_group(row: ElementRef<'_>) -> Option<&str> {
+    row_group_element(row).map(|group| group.value().name())
 }
 
-fn save_tables(tables: &[Vec<Vec<String>>], output_dir: &PathBuf) -> Result<()> {
-    fs::create_dir_all(output_dir)?;
+/// Whether a row holds column headers: it's in a `<thead>`, or all its
+/// cells are header cells and it isn't in a `<tfoot>`. In a table with a
+/// `<thead>` only its rows count, so that a group heading made of `<th>`
+/// cells at the start of the body isn't taken for another header row.
+fn is_header_row(row: ElementRef, cell_selector: &Selector, has_thead: bool) -> bool {
+    match row_group(row) {
+        Some("thead") => true,
+        Some("tfoot") => false,
+        _ if has_thead => false,
+        _ => {
+            let mut cells = row.select(cell_selector).peekable();
+            cells.peek().is_some()
+                && cells.all(|cell| cell.value().name() == "th" || cell.value().attr("role") == Some("columnheader"))
+        }
+    }
+}
+
+/// Continues the rowspans from previous rows that cover the next columns of
+/// `current_row`.
+fn fill_rowspans(current_row: &mut Vec<Option<String>>, carried: &mut [Option<(String, usize)>]) {
+    while let Some(slot @ Some(_)) = carried.get_mut(current_row.len()) {
+        let (cell, remaining) = slot.as_mut().unwrap();
+        current_row.push(Some(cell.clone()));
+        *remaining -= 1;
+        if *remaining == 0 {
+            *slot = None;
+        }
+    }
+}
+
+/// Running state of an extraction over one or more documents.
+pub struct Extraction<'a> {
+    cli: &'a Cli,
+    processed: usize,
+    extracted: usize,
+    failures: usize,
+    /// Cached documents skipped because their tables are already saved
+    unchanged: usize,
+    combined: Vec<Table>,
+    /// Content hashes of the tables saved so far, with `--dedup-tables`
+    seen: HashSet<u64>,
+    /// Schemas of the extracted tables, for `--schema-out`
+    schemas: Vec<serde_json::Value>,
+}
+
+impl<'a> Extraction<'a> {
+    fn new(cli: &'a Cli) -> Self {
+        Extraction {
+            cli,
+            processed: 0,
+            extracted: 0,
+            failures: 0,
+            unchanged: 0,
+            combined: Vec::new(),
+            seen: HashSet::new(),
+            schemas: Vec::new(),
+        }
+    }
+
+    /// Extracts and saves the tables of a document. A single failing
+    /// document shouldn't abort a batch, so errors are reported and counted.
+    pub fn process(&mut self, source: &Source, html: &str) {
+        if let Err(err) = self.try_process(source, html) {
+            self.failed(source, err);
+        }
+    }
+
+    fn try_process(&mut self, source: &Source, html: &str) -> Result<()> {
+        if source.unchanged && output::writes_per_document(&self.cli.output) {
+            self.unchanged += 1;
+            return Ok(());
+        }
+
+        self.save_document(source, html)?;
+        let tables = self.extract(source, html)?;
+        self.save(source, tables)
+    }
+
+    /// Keeps a copy of the document next to its tables with `--save-html`.
+    pub fn save_document(&self, source: &Source, html: &str) -> Result<()> {
+        if !self.cli.output.save_html {
+            return Ok(());
+        }
+        let extension = if input::is_markdown(&source.location) { "md" } else { "html" };
+        output::save_document(html, extension, &source.output_subdir, &self.cli.output)
+    }
+
+    /// Extracts the tables of a document that the command line asks for.
+    pub fn extract(&self, source: &Source, html: &str) -> Result<Vec<Table>> {
+        let mut tables = if input::is_markdown(&source.location) {
+            markdown::extract_tables(html, &source.location, &self.cli.extract)?
+        } else {
+            let url = source
+                .final_url
+                .as_deref()
+                .or(Some(source.location.as_str()).filter(|location| input::is_url(location)))
+                .and_then(|url| Url::parse(url).ok());
+            extract_tables(html, &source.location, url.as_ref(), &self.cli.extract, &self.cli.selection)?
+        };
+        for table in &mut tables {
+            self.cli.transform.apply(table);
+        }
+        tables.retain(|table| self.cli.selection.matches(table));
+        for table in &mut tables {
+            self.cli.transform.reshape(table);
+            table.snapshot.clone_from(&source.snapshot);
+            table.final_url.clone_from(&source.final_url);
+        }
+        Ok(tables)
+    }
+
+    /// Saves the tables of a document in the requested format, unless they
+    /// all go to one file or the clipboard.
+    pub fn save(&mut self, source: &Source, mut tables: Vec<Table>) -> Result<()> {
+        // Stitched pages may repeat rows of one another
+        for table in &mut tables {
+            self.cli.transform.dedup(table);
+        }
+        if self.cli.selection.dedup_tables {
+            tables.retain(|table| self.seen.insert(select::content_hash(table)));
+        }
+        if self.cli.selection.interactive {
+            tables = select::pick_tables(tables, &source.location)?;
+        }
+        if self.cli.output.schema_out.is_some() {
+            self.schemas.extend(tables.iter().map(schema::table_schema));
+        }
+        if self.cli.output.list {
+            output::list_tables(&tables, &source.location);
+            self.extracted += tables.len();
+        } else if self.cli.output.preview {
+            output::preview_tables(&tables, &source.location, self.cli.output.max_rows);
+            self.extracted += tables.len();
+        } else if self.cli.output.single_file || self.cli.output.to_clipboard || self.cli.output.merge_same_schema {
+            self.extracted += tables.len();
+            self.combined.extend(tables);
+        } else if !tables.is_empty() {
+            output::save_tables(&tables, &source.output_subdir, &self.cli.output)?;
+            self.extracted += tables.len();
+        }
+        self.processed += 1;
+        Ok(())
+    }
+
+    /// Processes the documents embedded in a page, as loaded by
+    /// `input::load_iframes`.
+    pub fn process_frames(&mut self, frames: Vec<(Source, Result<String>)>) {
+        for (frame, html) in frames {
+            match html {
+                Ok(html) => self.process(&frame, &html),
+                Err(err) => self.failed(&frame, err),
+            }
+        }
+    }
+
+    pub fn failed(&mut self, source: &Source, err: anyhow::Error) {
+        eprintln!("Failed to process {}: {:#}", source.location, err);
+        self.failures += 1;
+    }
+
+    fn finish(mut self) -> Result<()> {
+        // Keep stdout clean for the tables when they are written there
+        let status = |message: &str| {
+            if output::is_stdout(&self.cli.output.output_dir) {
+                eprintln!("{}", message);
+            } else {
+                println!("{}", message);
+            }
+        };
 
-    for (i, table) in tables.iter().enumerate() {
-        let filename = output_dir.join(format!("table_{}.csv", i + 1));
-        let mut writer = Writer::from_path(&filename)?;
+        if self.extracted > 0 && (self.cli.output.list || self.cli.output.preview) {
+            status(&format!("Found {} tables", self.extracted));
+        } else if self.extracted > 0 {
+            if self.cli.output.merge_same_schema {
+                self.combined = merge_same_schema(std::mem::take(&mut self.combined));
+                for table in &mut self.combined {
+                    self.cli.transform.dedup(table);
+                }
+            }
+            if !self.combined.is_empty() {
+                output::save_tables(&self.combined, Path::new(""), &self.cli.output)?;
+            }
+            status(&format!("Successfully extracted {} tables!", self.extracted));
+        } else if self.unchanged == 0 {
+            status("No tables found in the input source.");
+        }
+        if let Some(path) = &self.cli.output.schema_out {
+            schema::write_schemas(self.schemas, path)?;
+        }
+        if self.unchanged > 0 {
+            status(&format!("Skipped {} documents unchanged since the last run", self.unchanged));
+        }
 
-        for row in table {
-            writer.write_record(row)?;
+        if self.failures > 0 {
+            let total = self.processed + self.unchanged + self.failures;
+            bail!("{} of {} documents could not be processed", self.failures, total);
         }
-        writer.flush()?;
+        Ok(())
     }
+}
 
-    Ok(())
+/// Concatenates the tables with matching header rows, wherever they come
+/// from, after adding a first `source` column to each.
+fn merge_same_schema(tables: Vec<Table>) -> Vec<Table> {
+    let mut merged = Vec::new();
+    for mut table in tables {
+        let sources = vec![table.source.clone(); table.rows.len()];
+        transform::insert_first_column(&mut table, "source", sources);
+        paginate::stitch(&mut merged, vec![table]);
+    }
+    merged
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let mut extraction = Extraction::new(&cli);
+    let client = http::Client::new(&cli.http)?;
+    client.start_session().await?;
 
-    // Fetch HTML content
-    let html_content = fetch_html(&cli.input).await?;
-
-    // Extract tables
-    let tables = extract_tables(&html_content)?;
+    if let Some(Command::Crawl(args)) = &cli.command {
+        crawl::run(args, &cli.input, &client, &mut extraction).await;
+        return extraction.finish();
+    }
 
-    if tables.is_empty() {
-        println!("No tables found in the input source.");
-        return Ok(());
+    let sources = input::resolve(&cli.input, &client).await?;
+    if cli.pagination.applies_to(&sources) {
+        paginate::run(sources, &cli.pagination, &cli.input, &client, &mut extraction).await;
+        return extraction.finish();
     }
+    let single = sources.len() == 1 && !sources[0].is_warc();
+
+    // Fetch HTML content, possibly several documents at a time
+    let input_args = &cli.input;
+    let client = &client;
+    let mut documents = stream::iter(input::documents(sources))
+        .map(|mut source| async move {
+            let html = input::load(&mut source, input_args, client).await;
+            let frames = match &html {
+                Ok(html) if input_args.follow_iframes => input::load_iframes(&source, html, input_args, client).await,
+                _ => Vec::new(),
+            };
+            (source, html, frames)
+        })
+        .buffered(cli.concurrency.into());
 
-    // Save tables as CSV files
-    save_tables(&tables, &cli.output_dir)?;
-    println!("Successfully extracted {} tables!", tables.len());
+    while let Some((source, html_content, frames)) = documents.next().await {
+        match html_content {
+            Ok(html) if single => extraction.try_process(&source, &html)?,
+            Ok(html) => extraction.process(&source, &html),
+            Err(err) if single => return Err(err),
+            Err(err) => extraction.failed(&source, err),
+        }
+        extraction.process_frames(frames);
+    }
 
-    Ok(())
+    extraction.finish()
 }
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+
+use crate::extract::ExtractArgs;
+use crate::Table;
+
+/// Extracts GitHub-flavored pipe tables from a Markdown document. A table
+/// is a header row followed by a delimiter row (`| --- | :-: |`) and runs
+/// until the first blank line or line without a pipe. Rows are cut or
+/// padded to the header's width, as GitHub does. Of the extraction options,
+/// only the cell length limit applies.
+pub fn extract_tables(markdown: &str, source: &str, extract: &ExtractArgs) -> Result<Vec<Table>> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut tables = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut heading = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim();
+
+        // Pipes inside fenced code blocks aren't tables
+        if let Some(marker) = fence {
+            if line.starts_with(marker) {
+                fence = None;
+            }
+            i += 1;
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| line.starts_with(marker)) {
+            fence = Some(marker);
+            i += 1;
+            continue;
+        }
+
+        if let Some(text) = atx_heading(line) {
+            heading = Some(text);
+            i += 1;
+            continue;
+        }
+
+        let header = split_row(line);
+        let is_table = line.contains('|')
+            && lines.get(i + 1).is_some_and(|next| {
+                let delimiter = split_row(next.trim());
+                delimiter.len() == header.len() && delimiter.iter().all(|cell| is_delimiter_cell(cell))
+            });
+        if !is_table {
+            i += 1;
+            continue;
+        }
+
+        let width = header.len();
+        let mut rows = vec![header];
+        i += 2;
+        while let Some(line) = lines.get(i).map(|line| line.trim()) {
+            if line.is_empty() || !line.contains('|') {
+                break;
+            }
+            let mut row = split_row(line);
+            row.resize(width, String::new());
+            rows.push(row);
+            i += 1;
+        }
+
+        let mut table = Table {
+            source: source.to_string(),
+            index: tables.len() + 1,
+            heading: heading.clone(),
+            rows,
+            header_rows: 1,
+            ..Table::default()
+        };
+        extract
+            .limit_cell_length(&mut table)
+            .with_context(|| format!("Table {} has an overlong cell", table.index))?;
+        tables.push(table);
+    }
+
+    Ok(tables)
+}
+
+/// The text of an ATX heading line such as `## Results ##`.
+fn atx_heading(line: &str) -> Option<String> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Splits a table row into its trimmed cells, honoring `\|` escapes.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cell.push('|');
+                chars.next();
+            }
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn is_delimiter_cell(cell: &str) -> bool {
+    let dashes = cell.strip_prefix(':').unwrap_or(cell);
+    let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+    !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        extract: ExtractArgs,
+    }
+
+    fn tables(markdown: &str) -> Vec<Table> {
+        extract_tables(markdown, "test.md", &Cli::parse_from(["test"]).extract).unwrap()
+    }
+
+    #[test]
+    fn splits_rows_into_cells() {
+        assert_eq!(split_row("| a | b |"), ["a", "b"]);
+        assert_eq!(split_row("a | b"), ["a", "b"]);
+        assert_eq!(split_row("| a \\| b | c |"), ["a | b", "c"]);
+        assert_eq!(split_row("| a | b \\|"), ["a", "b |"]);
+        assert_eq!(split_row("| | x |"), ["", "x"]);
+    }
+
+    #[test]
+    fn recognizes_delimiter_cells() {
+        assert!(["---", ":--", "--:", ":-:", "-"].into_iter().all(is_delimiter_cell));
+        assert!(!["", ":", "::", "-x-", "= ="].into_iter().any(is_delimiter_cell));
+    }
+
+    #[test]
+    fn reads_pipe_tables() {
+        let tables = tables(
+            "## Results ##\n\n| Name | Score |\n|:-----|------:|\n| Ann | 3 | extra |\n| Bob |\n\nAfter | the table\n",
+        );
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].heading.as_deref(), Some("Results"));
+        assert_eq!(tables[0].header_rows, 1);
+        assert_eq!(tables[0].rows, [vec!["Name", "Score"], vec!["Ann", "3"], vec!["Bob", ""]]);
+    }
+
+    #[test]
+    fn needs_a_matching_delimiter_row() {
+        assert!(tables("| a | b |\n| --- |\n| 1 | 2 |\n").is_empty());
+        assert!(tables("| a | b |\n| 1 | 2 |\n").is_empty());
+    }
+
+    #[test]
+    fn skips_fenced_code() {
+        let markdown = "```\n| a | b |\n| - | - |\n```\n~~~\n| c |\n| - |\n~~~\n| d |\n| - |\n| 1 |\n";
+        let tables = tables(markdown);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].rows, [vec!["d"], vec!["1"]]);
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use arrow_array::{ArrayRef, BooleanArray, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray};
+use chrono::NaiveDate;
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::schema::{infer_column_types, parse_bool, parse_date, ColumnType};
+use crate::Table;
+
+pub fn write(table: &Table, path: &Path) -> Result<()> {
+    let batch = record_batch(table)?;
+
+    let mut writer = FileWriter::try_new(File::create(path)?, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Converts a table into an Arrow record batch, using the first row as
+/// column names and the inferred column types for the data rows.
+pub fn record_batch(table: &Table) -> Result<RecordBatch> {
+    let names = table.column_names();
+    let types = infer_column_types(table);
+    let rows = table.data_rows();
+
+    let mut fields = Vec::with_capacity(names.len());
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(names.len());
+
+    for (col, (name, ty)) in names.into_iter().zip(types).enumerate() {
+        let values = rows.iter().map(|row| {
+            row.get(col)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        });
+
+        let (data_type, array): (DataType, ArrayRef) = match ty {
+            ColumnType::Integer => (
+                DataType::Int64,
+                Arc::new(values.map(|v| v.and_then(|v| v.parse().ok())).collect::<Int64Array>()),
+            ),
+            ColumnType::Float => (
+                DataType::Float64,
+                Arc::new(values.map(|v| v.and_then(|v| v.parse().ok())).collect::<Float64Array>()),
+            ),
+            ColumnType::Boolean => (
+                DataType::Boolean,
+                Arc::new(values.map(|v| v.and_then(parse_bool)).collect::<BooleanArray>()),
+            ),
+            ColumnType::Date => (
+                DataType::Date32,
+                Arc::new(
+                    values
+                        .map(|v| v.and_then(parse_date).map(|date| (date - NaiveDate::default()).num_days() as i32))
+                        .collect::<Date32Array>(),
+                ),
+            ),
+            ColumnType::String => (
+                DataType::Utf8,
+                Arc::new(
+                    rows.iter()
+                        .map(|row| row.get(col).map(String::as_str))
+                        .collect::<StringArray>(),
+                ),
+            ),
+        };
+
+        fields.push(Field::new(name, data_type, true));
+        columns.push(array);
+    }
+
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?)
+}
@@ -0,0 +1,185 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use csv::{Terminator, WriterBuilder};
+use std::io::Write;
+use std::path::Path;
+
+use super::open_output;
+use crate::Table;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum QuoteStyle {
+    /// Quote every field
+    Always,
+    /// Quote fields only when they contain special characters
+    Necessary,
+    /// Quote every field that isn't a number
+    NonNumeric,
+    /// Never quote fields
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FormulaGuard {
+    /// Prefix the cell with `'`, which spreadsheets read as plain text
+    Quote,
+    /// Remove the leading formula characters
+    Strip,
+}
+
+/// Settings for the underlying CSV writer.
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub quote_style: QuoteStyle,
+    pub quote: u8,
+    /// Escape quotes with this character instead of doubling them
+    pub escape: Option<u8>,
+    pub crlf: bool,
+    /// Start new files with a UTF-8 byte order mark
+    pub bom: bool,
+    /// Start every table with a `#` comment line naming it
+    pub comment: bool,
+    /// Keep cells from being run as spreadsheet formulas
+    pub formulas: Option<FormulaGuard>,
+    /// Placeholder written in place of blank data cells
+    pub empty_value: Option<String>,
+}
+
+impl CsvOptions {
+    /// A CSV writer over `writer`, starting with the comment line for
+    /// `table` if given.
+    fn writer<W: Write>(&self, mut writer: W, new_file: bool, table: Option<&Table>) -> Result<csv::Writer<W>> {
+        if self.bom && new_file {
+            writer.write_all("\u{feff}".as_bytes())?;
+        }
+        if let Some(table) = table {
+            self.write_comment(table, &mut writer)?;
+        }
+
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .quote_style(match self.quote_style {
+                QuoteStyle::Always => csv::QuoteStyle::Always,
+                QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+                QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+                QuoteStyle::Never => csv::QuoteStyle::Never,
+            })
+            .terminator(if self.crlf { Terminator::CRLF } else { Terminator::Any(b'\n') });
+        if let Some(escape) = self.escape {
+            builder.double_quote(false).escape(escape);
+        }
+        Ok(builder.from_writer(writer))
+    }
+
+    /// Writes the comment line for a table, made of its heading and
+    /// caption, if it has either.
+    fn write_comment(&self, table: &Table, mut writer: impl Write) -> Result<()> {
+        let parts: Vec<&str> = [&table.heading, &table.caption].into_iter().flatten().map(String::as_str).collect();
+        if self.comment && !parts.is_empty() {
+            writer.write_all(format!("# {}", parts.join(" / ")).as_bytes())?;
+            writer.write_all(self.line_ending())?;
+        }
+        Ok(())
+    }
+
+    /// Writes a row, putting the placeholder for blank cells in data rows and
+    /// defusing cells a spreadsheet would take for a formula: those starting
+    /// with `=`, `+`, `-` or `@` that aren't plain numbers.
+    fn write_row<W: Write>(&self, writer: &mut csv::Writer<W>, row: &[String], data: bool) -> Result<()> {
+        const FORMULA_START: [char; 4] = ['=', '+', '-', '@'];
+
+        writer.write_record(row.iter().map(|cell| match (&self.empty_value, self.formulas) {
+            (Some(empty), _) if data && cell.trim().is_empty() => empty.clone(),
+            (_, Some(guard)) if cell.starts_with(FORMULA_START) && cell.trim().parse::<f64>().is_err() => match guard {
+                FormulaGuard::Quote => format!("'{}", cell),
+                FormulaGuard::Strip => cell.trim_start_matches(FORMULA_START).to_string(),
+            },
+            _ => cell.clone(),
+        }))?;
+        Ok(())
+    }
+
+    fn line_ending(&self) -> &'static [u8] {
+        if self.crlf {
+            b"\r\n"
+        } else {
+            b"\n"
+        }
+    }
+}
+
+pub fn write(table: &Table, path: &Path, options: &CsvOptions, append: bool) -> Result<()> {
+    let (file, has_content) = open_output(path, append)?;
+    let mut writer = options.writer(file, !has_content, (!has_content).then_some(table))?;
+
+    let header_len = table.header().len();
+    let skip = if has_content { header_len } else { 0 };
+    for (r, row) in table.rows.iter().enumerate().skip(skip) {
+        options.write_row(&mut writer, row, r >= header_len)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Streams all tables to a single writer, separated by blank lines.
+/// `continued` tells that the writer already holds earlier tables.
+pub fn write_to(tables: &[Table], mut writer: impl Write, options: &CsvOptions, continued: bool) -> Result<()> {
+    for (i, table) in tables.iter().enumerate() {
+        let first = i == 0 && !continued;
+        if !first {
+            writer.write_all(options.line_ending())?;
+        }
+
+        let mut table_writer = options.writer(&mut writer, first, Some(table))?;
+        let header_len = table.header().len();
+        for (r, row) in table.rows.iter().enumerate() {
+            options.write_row(&mut table_writer, row, r >= header_len)?;
+        }
+        table_writer.flush()?;
+    }
+
+    Ok(())
+}
+
+pub fn write_combined(tables: &[Table], path: &Path, options: &CsvOptions, append: bool) -> Result<()> {
+    let (file, has_content) = open_output(path, append)?;
+    write_combined_to(tables, file, options, !has_content)
+}
+
+/// Concatenates all tables into a single file, prefixing every row with the
+/// table's source, its index in that source and the row number.
+pub fn write_combined_to(
+    tables: &[Table],
+    writer: impl Write,
+    options: &CsvOptions,
+    new_file: bool,
+) -> Result<()> {
+    let width = tables.iter().map(Table::width).max().unwrap_or(0);
+    let mut writer = options.writer(writer, new_file, None)?;
+
+    let mut header = vec![
+        "source".to_string(),
+        "table_index".to_string(),
+        "row_index".to_string(),
+    ];
+    header.extend((1..=width).map(|c| format!("column_{}", c)));
+    if new_file {
+        writer.write_record(&header)?;
+    }
+
+    for table in tables {
+        for (r, row) in table.rows.iter().enumerate() {
+            let mut record = vec![table.source.clone(), table.index.to_string(), (r + 1).to_string()];
+            record.extend(row.iter().cloned());
+            record.resize(header.len(), String::new());
+            options.write_row(&mut writer, &record, r >= table.header().len())?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
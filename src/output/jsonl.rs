@@ -0,0 +1,50 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::open_output;
+use crate::schema::{infer_column_types, parse_bool, ColumnType};
+use crate::Table;
+
+pub fn write(table: &Table, path: &Path, append: bool) -> Result<()> {
+    let (file, _) = open_output(path, append)?;
+    write_to(std::slice::from_ref(table), file)
+}
+
+/// Streams every data row as a JSON object keyed by column name, one object
+/// per line. Values of numeric and boolean columns are written as such, and
+/// empty cells of typed columns as `null`.
+pub fn write_to(tables: &[Table], writer: impl Write) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+
+    for table in tables {
+        let names = table.column_names();
+        let types = infer_column_types(table);
+        for row in table.data_rows() {
+            let object: serde_json::Map<String, Value> = names
+                .iter()
+                .zip(&types)
+                .zip(row.iter().map(String::as_str).chain(std::iter::repeat("")))
+                .map(|((name, ty), value)| (name.clone(), json_value(value, *ty)))
+                .collect();
+            serde_json::to_writer(&mut writer, &object)?;
+            writer.write_all(b"\n")?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn json_value(value: &str, ty: ColumnType) -> Value {
+    let trimmed = value.trim();
+    match ty {
+        ColumnType::String => value.into(),
+        _ if trimmed.is_empty() => Value::Null,
+        ColumnType::Date => trimmed.into(),
+        ColumnType::Integer => trimmed.parse::<i64>().map_or(Value::Null, Value::from),
+        ColumnType::Float => trimmed.parse::<f64>().map_or(Value::Null, Value::from),
+        ColumnType::Boolean => parse_bool(trimmed).map_or(Value::Null, Value::from),
+    }
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{Span, Table};
+
+pub fn write(table: &Table, path: &Path) -> Result<()> {
+    write_to(std::slice::from_ref(table), File::create(path)?, false)
+}
+
+/// Writes each table as a booktabs `tabular`, wrapped in a `table` float
+/// when it has a caption. Merged cells become `\multicolumn`/`\multirow`.
+pub fn write_to(tables: &[Table], writer: impl Write, continued: bool) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+
+    for (i, table) in tables.iter().enumerate() {
+        if i > 0 || continued {
+            writeln!(writer)?;
+        }
+        if let Some(caption) = &table.caption {
+            writeln!(writer, "\\begin{{table}}")?;
+            writeln!(writer, "\\centering")?;
+            writeln!(writer, "\\caption{{{}}}", escape(caption))?;
+        }
+        writeln!(writer, "\\begin{{tabular}}{{{}}}", "l".repeat(table.width()))?;
+        writeln!(writer, "\\toprule")?;
+
+        for (r, line) in rows(table).iter().enumerate() {
+            writeln!(writer, "{} \\\\", line)?;
+            if r + 1 == table.header().len() && table.rows.len() > r + 1 {
+                writeln!(writer, "\\midrule")?;
+            }
+        }
+
+        writeln!(writer, "\\bottomrule")?;
+        writeln!(writer, "\\end{{tabular}}")?;
+        if table.caption.is_some() {
+            writeln!(writer, "\\end{{table}}")?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Renders the body of every row, placing the content of a merged cell only
+/// at its origin and leaving the rest of the area it covers blank.
+fn rows(table: &Table) -> Vec<String> {
+    let mut covering: HashMap<(usize, usize), &Span> = HashMap::new();
+    for span in &table.spans {
+        for r in span.row..span.row + span.rowspan {
+            covering.insert((r, span.col), span);
+        }
+    }
+
+    table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(r, row)| {
+            let mut cells = Vec::new();
+            let mut c = 0;
+            while c < row.len() {
+                let Some(span) = covering.get(&(r, c)) else {
+                    cells.push(escape(&row[c]));
+                    c += 1;
+                    continue;
+                };
+
+                let mut content = if span.row == r { escape(&row[c]) } else { String::new() };
+                if span.row == r && span.rowspan > 1 {
+                    content = format!("\\multirow{{{}}}{{*}}{{{}}}", span.rowspan, content);
+                }
+                if span.colspan > 1 {
+                    content = format!("\\multicolumn{{{}}}{{l}}{{{}}}", span.colspan, content);
+                }
+                cells.push(content);
+                c += span.colspan;
+            }
+            cells.join(" & ")
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,86 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::Table;
+
+/// Provenance and structure of a table, as written to the sidecar files.
+fn table_metadata(table: &Table, extracted_at: &DateTime<Utc>) -> Value {
+    let mut metadata = json!({
+        "source": table.source,
+        "extracted_at": extracted_at.to_rfc3339(),
+        "table_index": table.index,
+        "caption": table.caption,
+        "heading": table.heading,
+        "id": table.id,
+        "classes": table.classes,
+        "part": table.part,
+        "rows": table.rows.len(),
+        "columns": table.width(),
+        "spans": table.spans,
+    });
+    if let Some(snapshot) = &table.snapshot {
+        metadata["snapshot_timestamp"] = json!(snapshot);
+    }
+    if let Some(final_url) = &table.final_url {
+        metadata["final_url"] = json!(final_url);
+    }
+    metadata
+}
+
+/// Layout of the merged cells of a table in its output grid, as written to
+/// the span map files: the top-left cell, extent and value of each.
+fn span_map(table: &Table) -> Value {
+    let cells: Vec<Value> = table
+        .spans
+        .iter()
+        .map(|span| {
+            json!({
+                "row": span.row,
+                "col": span.col,
+                "rowspan": span.rowspan,
+                "colspan": span.colspan,
+                "value": table.rows.get(span.row).and_then(|row| row.get(span.col)),
+            })
+        })
+        .collect();
+    json!({
+        "source": table.source,
+        "table_index": table.index,
+        "rows": table.rows.len(),
+        "columns": table.width(),
+        "header_rows": table.header().len(),
+        "cells": cells,
+    })
+}
+
+pub fn write(table: &Table, path: &Path, extracted_at: &DateTime<Utc>) -> Result<()> {
+    write_value(&table_metadata(table, extracted_at), path)
+}
+
+pub fn write_combined(tables: &[Table], path: &Path, extracted_at: &DateTime<Utc>) -> Result<()> {
+    let values: Vec<Value> = tables
+        .iter()
+        .map(|table| table_metadata(table, extracted_at))
+        .collect();
+    write_value(&Value::Array(values), path)
+}
+
+pub fn write_span_map(table: &Table, path: &Path) -> Result<()> {
+    write_value(&span_map(table), path)
+}
+
+pub fn write_combined_span_maps(tables: &[Table], path: &Path) -> Result<()> {
+    write_value(&Value::Array(tables.iter().map(span_map).collect()), path)
+}
+
+fn write_value(value: &Value, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,611 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use clap::{Args, ValueEnum};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::clipboard;
+use crate::Table;
+use csv::CsvOptions;
+
+mod arrow;
+mod csv;
+mod jsonl;
+mod latex;
+mod metadata;
+mod ods;
+mod parquet;
+mod preview;
+mod sql;
+mod sqlite;
+mod xlsx;
+
+pub use csv::{FormulaGuard, QuoteStyle};
+pub use preview::print as preview_tables;
+pub use sql::Dialect;
+
+#[derive(Args)]
+pub struct OutputArgs {
+    /// Output directory, or `-` to write to stdout
+    #[arg(short, long, alias = "output", default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Csv)]
+    pub format: OutputFormat,
+
+    /// File name for each table, from `{index}`, `{caption}`, `{heading}`
+    /// and `{id}`, e.g. `{heading}-{index}`; by default its caption or `id`,
+    /// or else `table_{index}`
+    #[arg(long, value_name = "TEMPLATE")]
+    pub name_template: Option<String>,
+
+    /// Field delimiter for CSV/TSV output: a single character, `\t` or `tab`
+    #[arg(short, long, value_parser = parse_ascii_char)]
+    pub delimiter: Option<u8>,
+
+    /// When to quote CSV/TSV fields
+    #[arg(long, value_enum, default_value_t = QuoteStyle::Necessary)]
+    pub quote_style: QuoteStyle,
+
+    /// Keep CSV/TSV cells starting with `=`, `+`, `-` or `@` from running
+    /// as formulas in spreadsheets, by quoting them or stripping those
+    /// characters
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub sanitize_formulas: Option<FormulaGuard>,
+
+    /// Write blank CSV/TSV data cells, including those covered by a merged
+    /// cell with `--span-fill empty`, as this placeholder, e.g. `NA`
+    #[arg(long, value_name = "TEXT")]
+    pub empty_value: Option<String>,
+
+    /// Quote character for CSV/TSV output
+    #[arg(long, value_parser = parse_ascii_char, default_value = "\"")]
+    pub quote: u8,
+
+    /// Escape quotes inside fields with this character instead of doubling them
+    #[arg(long, value_parser = parse_ascii_char)]
+    pub escape: Option<u8>,
+
+    /// End CSV/TSV lines with CRLF instead of LF
+    #[arg(long)]
+    pub crlf: bool,
+
+    /// Start CSV/TSV files with a UTF-8 byte order mark, for Excel
+    #[arg(long)]
+    pub bom: bool,
+
+    /// Start CSV/TSV tables with a `# heading / caption` comment line
+    #[arg(long)]
+    pub csv_comment: bool,
+
+    /// Write all tables into a single CSV/TSV file with provenance columns
+    #[arg(long)]
+    pub single_file: bool,
+
+    /// Merge the tables of all inputs whose header rows match into one
+    /// table, with a first `source` column
+    #[arg(long)]
+    pub merge_same_schema: bool,
+
+    /// SQL dialect used by the `sql` output format
+    #[arg(long, value_enum, default_value_t = Dialect::Postgres)]
+    pub dialect: Dialect,
+
+    /// Print the position, name, size and header row of every table found
+    /// instead of writing any output
+    #[arg(long)]
+    pub list: bool,
+
+    /// Show the tables in the terminal instead of writing any output
+    #[arg(long, conflicts_with = "list")]
+    pub preview: bool,
+
+    /// Data rows shown per table with `--preview`
+    #[arg(long, value_name = "N", default_value_t = 20, requires = "preview")]
+    pub max_rows: usize,
+
+    /// Copy the CSV/TSV output to the clipboard instead of writing files
+    #[arg(long)]
+    pub to_clipboard: bool,
+
+    /// Write a `.meta.json` file with provenance and structure next to each output file
+    #[arg(long)]
+    pub metadata: bool,
+
+    /// Write a `.spans.json` file with the position, extent and value of
+    /// every merged cell next to each output file
+    #[arg(long)]
+    pub span_map: bool,
+
+    /// Write the column names and inferred types of every table to this
+    /// JSON file
+    #[arg(long, value_name = "PATH")]
+    pub schema_out: Option<PathBuf>,
+
+    /// Keep a copy of every processed document as `source.html` (or
+    /// `source.md`) next to its tables
+    #[arg(long)]
+    pub save_html: bool,
+
+    /// Overwrite existing output files (same as `--on-conflict overwrite`)
+    #[arg(long)]
+    pub force: bool,
+
+    /// Append rows to existing CSV/TSV/JSONL files, skipping the header row
+    #[arg(long, conflicts_with_all = ["force", "on_conflict"])]
+    pub append: bool,
+
+    /// What to do when an output file already exists
+    #[arg(long, value_enum, default_value_t = ConflictPolicy::Error)]
+    pub on_conflict: ConflictPolicy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Fail without writing anything over the existing file
+    Error,
+    /// Leave the existing file alone and don't write the new one
+    Skip,
+    /// Write to the first free `name-N` path instead
+    Suffix,
+    /// Replace the existing file
+    Overwrite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One CSV file per table
+    Csv,
+    /// One tab-separated file per table
+    Tsv,
+    /// A single Excel workbook with one sheet per table
+    Xlsx,
+    /// One Parquet file per table, with column types inferred
+    Parquet,
+    /// One JSON object per row, one file per table
+    Jsonl,
+    /// A single SQLite database with one SQL table per table
+    Sqlite,
+    /// A single SQL script with CREATE TABLE and INSERT statements
+    Sql,
+    /// One LaTeX booktabs tabular per table
+    Latex,
+    /// One Arrow IPC (Feather v2) file per table, with column types inferred
+    Arrow,
+    /// A single OpenDocument spreadsheet with one sheet per table
+    Ods,
+}
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Xlsx => "xlsx",
+            OutputFormat::Parquet => "parquet",
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Sqlite => "db",
+            OutputFormat::Sql => "sql",
+            OutputFormat::Latex => "tex",
+            OutputFormat::Arrow => "arrow",
+            OutputFormat::Ods => "ods",
+        }
+    }
+
+    /// Whether the format writes all tables into a single file.
+    fn is_combined(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Xlsx | OutputFormat::Sqlite | OutputFormat::Sql | OutputFormat::Ods
+        )
+    }
+}
+
+fn parse_ascii_char(value: &str) -> Result<u8, String> {
+    match value {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if value.len() == 1 => Ok(value.as_bytes()[0]),
+        _ => Err("must be a single ASCII character".to_string()),
+    }
+}
+
+/// Whether tables were written to standard output already, by an earlier
+/// document, so the next ones have to be separated from them.
+static STDOUT_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the output location is `-`, meaning standard output.
+pub fn is_stdout(output_dir: &Path) -> bool {
+    output_dir == Path::new("-")
+}
+
+/// Whether every document's tables go to files of their own, so a document
+/// can be skipped without affecting the output of the others.
+pub fn writes_per_document(args: &OutputArgs) -> bool {
+    !is_stdout(&args.output_dir)
+        && !args.single_file
+        && !args.merge_same_schema
+        && !args.to_clipboard
+        && !args.list
+        && !args.preview
+        && !args.format.is_combined()
+}
+
+/// Prints a summary line for each table of a document, for `--list`.
+pub fn list_tables(tables: &[Table], source: &str) {
+    println!("{}", source);
+    for table in tables {
+        println!("  {}", summary(table));
+    }
+}
+
+/// One line describing a table: its position, name, size and the start of
+/// its header row.
+pub fn summary(table: &Table) -> String {
+    const SNIPPET_LEN: usize = 60;
+
+    let header = table.rows.first().map(|row| row.join(" | ")).unwrap_or_default();
+    let mut snippet: String = header.chars().take(SNIPPET_LEN).collect();
+    if header.chars().count() > SNIPPET_LEN {
+        snippet.push('…');
+    }
+    format!(
+        "{:>3}  {:<24}  {:>4} x {:<3}  {}",
+        table.index,
+        table.label().unwrap_or("-"),
+        table.rows.len(),
+        table.width(),
+        snippet
+    )
+}
+
+/// Writes the tables of one document to `subdir` inside the output directory.
+pub fn save_tables(tables: &[Table], subdir: &Path, args: &OutputArgs) -> Result<()> {
+    let output_dir = args.output_dir.join(subdir);
+    let output_dir = output_dir.as_path();
+    let format = args.format;
+    let csv_options = CsvOptions {
+        delimiter: args.delimiter.unwrap_or(match format {
+            OutputFormat::Tsv => b'\t',
+            _ => b',',
+        }),
+        quote_style: args.quote_style,
+        quote: args.quote,
+        escape: args.escape,
+        crlf: args.crlf,
+        bom: args.bom,
+        comment: args.csv_comment,
+        formulas: args.sanitize_formulas,
+        empty_value: args.empty_value.clone(),
+    };
+
+    if args.to_clipboard {
+        let mut buffer = Vec::new();
+        match format {
+            OutputFormat::Csv | OutputFormat::Tsv if args.single_file => {
+                csv::write_combined_to(tables, &mut buffer, &csv_options, true)?
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => csv::write_to(tables, &mut buffer, &csv_options, false)?,
+            _ => bail!("--to-clipboard is only supported for csv and tsv output"),
+        }
+        return clipboard::write_text(String::from_utf8(buffer)?);
+    }
+
+    if is_stdout(&args.output_dir) {
+        let stdout = io::stdout().lock();
+        let continued = STDOUT_STARTED.fetch_or(!tables.is_empty(), Ordering::Relaxed);
+        return match format {
+            OutputFormat::Csv | OutputFormat::Tsv if args.single_file => {
+                csv::write_combined_to(tables, stdout, &csv_options, true)
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => csv::write_to(tables, stdout, &csv_options, continued),
+            OutputFormat::Jsonl => jsonl::write_to(tables, stdout),
+            OutputFormat::Sql => sql::write_to(tables, args.dialect, stdout),
+            OutputFormat::Latex => latex::write_to(tables, stdout, continued),
+            _ => bail!("Writing {:?} output to stdout is not supported", format),
+        };
+    }
+
+    if args.single_file && !matches!(format, OutputFormat::Csv | OutputFormat::Tsv) {
+        bail!("--single-file is only supported for csv and tsv output");
+    }
+    if args.append && !matches!(format, OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Jsonl) {
+        bail!("--append is only supported for csv, tsv and jsonl output");
+    }
+
+    fs::create_dir_all(output_dir)?;
+    let extracted_at = Utc::now();
+    let policy = conflict_policy(args);
+
+    if args.single_file || format.is_combined() {
+        let path = output_dir.join(format!("tables.{}", format.extension()));
+        let Some(path) = resolve_conflict(path, policy)? else {
+            return Ok(());
+        };
+        if args.metadata {
+            metadata::write_combined(tables, &metadata_path(&path), &extracted_at)?;
+        }
+        if args.span_map {
+            metadata::write_combined_span_maps(tables, &path.with_extension("spans.json"))?;
+        }
+
+        return match format {
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                csv::write_combined(tables, &path, &csv_options, args.append)
+            }
+            OutputFormat::Xlsx => xlsx::write(tables, &path),
+            OutputFormat::Sqlite => sqlite::write(tables, &path),
+            OutputFormat::Sql => sql::write(tables, args.dialect, &path),
+            OutputFormat::Ods => ods::write(tables, &path),
+            _ => unreachable!("{:?} writes one file per table", format),
+        };
+    }
+
+    let paths = table_paths(tables, output_dir, format.extension(), args.name_template.as_deref());
+    if policy == ConflictPolicy::Error {
+        // Fail before writing anything rather than leaving a partial result
+        for path in &paths {
+            resolve_conflict(path.clone(), policy)?;
+        }
+    }
+
+    for (table, path) in tables.iter().zip(paths) {
+        let Some(path) = resolve_conflict(path, policy)? else {
+            continue;
+        };
+        if args.metadata {
+            metadata::write(table, &metadata_path(&path), &extracted_at)?;
+        }
+        if args.span_map {
+            metadata::write_span_map(table, &path.with_extension("spans.json"))?;
+        }
+
+        match format {
+            OutputFormat::Csv | OutputFormat::Tsv => csv::write(table, &path, &csv_options, args.append)?,
+            OutputFormat::Parquet => parquet::write(table, &path)?,
+            OutputFormat::Jsonl => jsonl::write(table, &path, args.append)?,
+            OutputFormat::Latex => latex::write(table, &path)?,
+            OutputFormat::Arrow => arrow::write(table, &path)?,
+            _ => unreachable!("{:?} writes a single file", format),
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the document tables were extracted from to `subdir` inside the
+/// output directory, for `--save-html`.
+pub fn save_document(content: &str, extension: &str, subdir: &Path, args: &OutputArgs) -> Result<()> {
+    if is_stdout(&args.output_dir) {
+        bail!("--save-html needs an output directory");
+    }
+
+    let output_dir = args.output_dir.join(subdir);
+    fs::create_dir_all(&output_dir)?;
+    let path = output_dir.join(format!("source.{}", extension));
+    if let Some(path) = resolve_conflict(path, conflict_policy(args))? {
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+fn conflict_policy(args: &OutputArgs) -> ConflictPolicy {
+    // Appending writes into existing files on purpose
+    if args.force || args.append {
+        ConflictPolicy::Overwrite
+    } else {
+        args.on_conflict
+    }
+}
+
+/// Sidecar metadata path for an output file: `table_1.csv` becomes
+/// `table_1.meta.json`.
+fn metadata_path(path: &Path) -> PathBuf {
+    path.with_extension("meta.json")
+}
+
+/// Opens an output file, truncating it unless `append` is set. Also returns
+/// whether the file already had content, in which case header rows shouldn't
+/// be written again.
+fn open_output(path: &Path, append: bool) -> Result<(File, bool)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)?;
+    let has_content = append && file.metadata()?.len() > 0;
+    Ok((file, has_content))
+}
+
+/// Applies the conflict policy to an output path, returning the path to write
+/// to or `None` if the file should be skipped.
+fn resolve_conflict(path: PathBuf, policy: ConflictPolicy) -> Result<Option<PathBuf>> {
+    if !path.exists() {
+        return Ok(Some(path));
+    }
+
+    match policy {
+        ConflictPolicy::Error => bail!(
+            "Output file already exists: {} (use --force or --on-conflict to change this)",
+            path.display()
+        ),
+        ConflictPolicy::Skip => {
+            eprintln!("Skipping existing file: {}", path.display());
+            Ok(None)
+        }
+        ConflictPolicy::Overwrite => Ok(Some(path)),
+        ConflictPolicy::Suffix => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let extension = path.extension().map(|ext| ext.to_string_lossy().into_owned());
+            let candidates = (2..).map(|counter| {
+                let name = match &extension {
+                    Some(ext) => format!("{}-{}.{}", stem, counter, ext),
+                    None => format!("{}-{}", stem, counter),
+                };
+                path.with_file_name(name)
+            });
+            Ok(candidates.into_iter().find(|candidate| !candidate.exists()))
+        }
+    }
+}
+
+/// One output path per table, named after the template, or else a slug of
+/// the table's caption or `id` when it has one and after its position in the
+/// document otherwise.
+fn table_paths(tables: &[Table], output_dir: &Path, extension: &str, template: Option<&str>) -> Vec<PathBuf> {
+    let mut used = HashSet::new();
+
+    tables
+        .iter()
+        .map(|table| {
+            let named = match template {
+                Some(template) => Some(fill_template(template, table)),
+                None => table.label().map(slugify),
+            };
+            let mut base = named
+                .filter(|slug| !slug.is_empty())
+                .unwrap_or_else(|| format!("table_{}", table.index));
+            if let Some(part) = &table.part {
+                base = format!("{}_{}", base, slugify(part));
+            }
+
+            let mut stem = base.clone();
+            let mut counter = 2;
+            while !used.insert(stem.clone()) {
+                stem = format!("{}-{}", base, counter);
+                counter += 1;
+            }
+            output_dir.join(format!("{}.{}", stem, extension))
+        })
+        .collect()
+}
+
+/// Fills a file name template with slugs of the table's fields; fields the
+/// table lacks are left out along with the separators around them.
+fn fill_template(template: &str, table: &Table) -> String {
+    let fields = [
+        ("{index}", Some(table.index.to_string())),
+        ("{caption}", table.caption.as_deref().map(slugify)),
+        ("{heading}", table.heading.as_deref().map(slugify)),
+        ("{id}", table.id.as_deref().map(slugify)),
+    ];
+    let mut name = template.to_string();
+    for (placeholder, value) in fields {
+        name = name.replace(placeholder, &value.unwrap_or_default());
+    }
+    name.trim_matches(['-', '_', '.', ' ']).replace("--", "-").replace("__", "_")
+}
+
+/// Lowercase ASCII letters and digits separated by single dashes, capped to
+/// a reasonable file name length.
+pub fn slugify(text: &str) -> String {
+    const MAX_LEN: usize = 60;
+
+    let mut slug = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_LEN);
+    slug.trim_end_matches('-').to_string()
+}
+
+/// SQL table and column identifiers for every table, derived from labels
+/// and header names.
+fn sql_names(tables: &[Table]) -> Vec<(String, Vec<String>)> {
+    let mut table_names = HashSet::new();
+
+    tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| {
+            let fallback = format!("table_{}", i + 1);
+            let name = sql_identifier(
+                table.label().unwrap_or(&fallback),
+                &fallback,
+                &mut table_names,
+            );
+
+            let mut column_names = HashSet::new();
+            let columns = table
+                .column_names()
+                .iter()
+                .enumerate()
+                .map(|(c, column)| {
+                    sql_identifier(column, &format!("column_{}", c + 1), &mut column_names)
+                })
+                .collect();
+
+            (name, columns)
+        })
+        .collect()
+}
+
+/// Turns arbitrary text into a lowercase SQL identifier made of ASCII
+/// letters, digits and underscores that doesn't start with a digit, and
+/// makes it unique among the names already in `used`.
+fn sql_identifier(text: &str, fallback: &str, used: &mut HashSet<String>) -> String {
+    let mut base = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            base.push(c);
+        } else if !base.is_empty() && !base.ends_with('_') {
+            base.push('_');
+        }
+    }
+    let mut base = base.trim_end_matches('_').to_string();
+    if base.is_empty() {
+        base = fallback.to_string();
+    }
+    if base.starts_with(|c: char| c.is_ascii_digit()) {
+        base.insert(0, '_');
+    }
+
+    let mut name = base.clone();
+    let mut counter = 2;
+    while !used.insert(name.clone()) {
+        name = format!("{}_{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+/// Builds a valid, unique spreadsheet sheet name from the table label, falling
+/// back to the table index. Excel limits names to 31 characters and forbids
+/// `[]:*?/\`; names are compared case-insensitively.
+fn sheet_name(table: &Table, index: usize, used: &mut HashSet<String>) -> String {
+    const MAX_LEN: usize = 31;
+
+    let base: String = table
+        .label()
+        .map(|label| {
+            label
+                .chars()
+                .map(|c| if "[]:*?/\\".contains(c) { ' ' } else { c })
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .trim_matches('\'')
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("Table {}", index + 1));
+
+    let mut name: String = base.chars().take(MAX_LEN).collect();
+    let mut counter = 2;
+    while !used.insert(name.to_lowercase()) {
+        let suffix = format!(" ({})", counter);
+        let keep = MAX_LEN - suffix.chars().count();
+        name = base.chars().take(keep).collect::<String>() + &suffix;
+        counter += 1;
+    }
+    name
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::sheet_name;
+use crate::Table;
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+pub fn write(tables: &[Table], path: &Path) -> Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+
+    // The mimetype entry has to come first and be stored uncompressed
+    zip.start_file(
+        "mimetype",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(b"application/vnd.oasis.opendocument.spreadsheet")?;
+
+    zip.start_file("META-INF/manifest.xml", SimpleFileOptions::default())?;
+    zip.write_all(MANIFEST.as_bytes())?;
+
+    zip.start_file("content.xml", SimpleFileOptions::default())?;
+    zip.write_all(content(tables).as_bytes())?;
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn content(tables: &[Table]) -> String {
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+        r#"<office:document-content"#,
+        r#" xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0""#,
+        r#" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0""#,
+        r#" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0""#,
+        r#" office:version="1.2"><office:body><office:spreadsheet>"#,
+    ));
+    let mut used_names = HashSet::new();
+
+    for (i, table) in tables.iter().enumerate() {
+        let name = sheet_name(table, i, &mut used_names);
+        xml.push_str(&format!(r#"<table:table table:name="{}">"#, escape(&name)));
+        for row in &table.rows {
+            xml.push_str("<table:table-row>");
+            for value in row {
+                xml.push_str(r#"<table:table-cell office:value-type="string">"#);
+                for line in value.lines() {
+                    xml.push_str(&format!("<text:p>{}</text:p>", escape(line)));
+                }
+                xml.push_str("</table:table-cell>");
+            }
+            xml.push_str("</table:table-row>");
+        }
+        xml.push_str("</table:table>");
+    }
+
+    xml.push_str("</office:spreadsheet></office:body></office:document-content>");
+    xml
+}
+
+/// Escapes text for XML, leaving out the control characters XML 1.0 doesn't
+/// allow, which would make the file unreadable.
+fn escape(text: &str) -> String {
+    let allowed = |c: &char| !matches!(c, '\u{0}'..='\u{8}' | '\u{b}' | '\u{c}' | '\u{e}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}');
+    text.chars()
+        .filter(allowed)
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,17 @@
+use anyhow::Result;
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::path::Path;
+
+use super::arrow::record_batch;
+use crate::Table;
+
+pub fn write(table: &Table, path: &Path) -> Result<()> {
+    let batch = record_batch(table)?;
+
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table as TextTable};
+
+use crate::Table;
+
+/// Pretty-prints tables to the terminal, header row highlighted and at most
+/// `max_rows` data rows each.
+pub fn print(tables: &[Table], source: &str, max_rows: usize) {
+    for table in tables {
+        let title = match table.label() {
+            Some(label) => format!("{} #{}: {}", source, table.index, label),
+            None => format!("{} #{}", source, table.index),
+        };
+        println!("{}", title);
+
+        let mut text = TextTable::new();
+        text.load_style(UTF8_FULL_CONDENSED)
+            .set_content_arrangement(ContentArrangement::Dynamic);
+        let header_cells =
+            |row: &[String]| row.iter().map(|name| Cell::new(name).add_attribute(Attribute::Bold).fg(Color::Cyan)).collect::<Vec<_>>();
+        if let Some((first, rest)) = table.header().split_first() {
+            text.set_header(header_cells(first));
+            for row in rest {
+                text.add_row(header_cells(row));
+            }
+        }
+        for row in table.data_rows().iter().take(max_rows) {
+            text.add_row(row);
+        }
+        println!("{}", text);
+
+        let hidden = table.data_rows().len().saturating_sub(max_rows);
+        if hidden > 0 {
+            println!("… {} more rows", hidden);
+        }
+        println!();
+    }
+}
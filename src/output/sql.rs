@@ -0,0 +1,109 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::sql_names;
+use crate::schema::{infer_column_types, parse_bool, ColumnType};
+use crate::Table;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Dialect {
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+impl Dialect {
+    pub fn quote_identifier(self, name: &str) -> String {
+        match self {
+            Dialect::Mysql => format!("`{}`", name.replace('`', "``")),
+            Dialect::Postgres | Dialect::Sqlite => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+
+    pub fn column_type(self, ty: ColumnType) -> &'static str {
+        match (self, ty) {
+            (Dialect::Sqlite, ColumnType::Integer) => "INTEGER",
+            (Dialect::Sqlite, ColumnType::Float) => "REAL",
+            (Dialect::Sqlite, ColumnType::Boolean) => "INTEGER",
+            (Dialect::Sqlite, ColumnType::Date) => "TEXT",
+            (_, ColumnType::Integer) => "BIGINT",
+            (Dialect::Postgres, ColumnType::Float) => "DOUBLE PRECISION",
+            (Dialect::Mysql, ColumnType::Float) => "DOUBLE",
+            (_, ColumnType::Boolean) => "BOOLEAN",
+            (_, ColumnType::Date) => "DATE",
+            (_, ColumnType::String) => "TEXT",
+        }
+    }
+
+    fn literal(self, value: &str, ty: ColumnType) -> String {
+        let trimmed = value.trim();
+        match ty {
+            _ if trimmed.is_empty() && ty != ColumnType::String => "NULL".to_string(),
+            ColumnType::Integer | ColumnType::Float => trimmed.to_string(),
+            ColumnType::Boolean => match (self, parse_bool(trimmed)) {
+                (_, None) => "NULL".to_string(),
+                (Dialect::Sqlite, Some(value)) => (value as u8).to_string(),
+                (_, Some(value)) => value.to_string().to_uppercase(),
+            },
+            ColumnType::Date => format!("'{}'", trimmed),
+            ColumnType::String => {
+                let mut escaped = value.replace('\'', "''");
+                if self == Dialect::Mysql {
+                    escaped = escaped.replace('\\', "\\\\");
+                }
+                format!("'{}'", escaped)
+            }
+        }
+    }
+
+    /// `DROP TABLE` and `CREATE TABLE` statements for a table with the given
+    /// columns.
+    pub fn create_table(self, name: &str, columns: &[String], types: &[ColumnType]) -> String {
+        let definitions: Vec<String> = columns
+            .iter()
+            .zip(types)
+            .map(|(column, ty)| format!("{} {}", self.quote_identifier(column), self.column_type(*ty)))
+            .collect();
+        let name = self.quote_identifier(name);
+
+        format!(
+            "DROP TABLE IF EXISTS {};\nCREATE TABLE {} ({});\n",
+            name,
+            name,
+            definitions.join(", ")
+        )
+    }
+}
+
+pub fn write(tables: &[Table], dialect: Dialect, path: &Path) -> Result<()> {
+    write_to(tables, dialect, File::create(path)?)
+}
+
+pub fn write_to(tables: &[Table], dialect: Dialect, writer: impl Write) -> Result<()> {
+    let mut writer = BufWriter::new(writer);
+
+    for (table, (name, columns)) in tables.iter().zip(sql_names(tables)) {
+        if columns.is_empty() {
+            continue;
+        }
+        let types = infer_column_types(table);
+        writer.write_all(dialect.create_table(&name, &columns, &types).as_bytes())?;
+
+        let name = dialect.quote_identifier(&name);
+        for row in table.data_rows() {
+            let values: Vec<String> = types
+                .iter()
+                .enumerate()
+                .map(|(c, ty)| dialect.literal(row.get(c).map(String::as_str).unwrap_or_default(), *ty))
+                .collect();
+            writeln!(writer, "INSERT INTO {} VALUES ({});", name, values.join(", "))?;
+        }
+        writeln!(writer)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anyhow::{Context, Result};
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection};
+use std::fs;
+use std::path::Path;
+
+use super::{sql_names, Dialect};
+use crate::schema::{infer_column_types, parse_bool, ColumnType};
+use crate::Table;
+
+/// Writes the tables to a new database at `path`, replacing any file there,
+/// which the conflict policy has already allowed. Tables without columns
+/// can't be created in SQL and are left out.
+pub fn write(tables: &[Table], path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path).with_context(|| format!("Failed to replace database: {}", path.display()))?;
+    }
+    let mut conn = Connection::open(path)
+        .with_context(|| format!("Failed to open database: {}", path.display()))?;
+    let tx = conn.transaction()?;
+
+    for (table, (name, columns)) in tables.iter().zip(sql_names(tables)) {
+        if columns.is_empty() {
+            continue;
+        }
+        let types = infer_column_types(table);
+        tx.execute_batch(&Dialect::Sqlite.create_table(&name, &columns, &types))?;
+
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO {} VALUES ({})",
+            Dialect::Sqlite.quote_identifier(&name),
+            placeholders
+        ))?;
+        for row in table.data_rows() {
+            let values = types.iter().enumerate().map(|(c, ty)| {
+                sql_value(row.get(c).map(String::as_str).unwrap_or_default(), *ty)
+            });
+            insert.execute(params_from_iter(values))?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+fn sql_value(value: &str, ty: ColumnType) -> Value {
+    let trimmed = value.trim();
+    match ty {
+        _ if trimmed.is_empty() && ty != ColumnType::String => Value::Null,
+        ColumnType::Integer => trimmed.parse().map_or(Value::Null, Value::Integer),
+        ColumnType::Float => trimmed.parse().map_or(Value::Null, Value::Real),
+        ColumnType::Boolean => parse_bool(trimmed).map_or(Value::Null, |value| Value::Integer(value as i64)),
+        ColumnType::Date => Value::Text(trimmed.to_string()),
+        ColumnType::String => Value::Text(value.to_string()),
+    }
+}
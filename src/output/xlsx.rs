@@ -0,0 +1,32 @@
+use anyhow::Result;
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::sheet_name;
+use crate::Table;
+
+pub fn write(tables: &[Table], path: &Path) -> Result<()> {
+    let mut workbook = Workbook::new();
+    let mut used_names = HashSet::new();
+    let bold = Format::new().set_bold();
+
+    for (i, table) in tables.iter().enumerate() {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet_name(table, i, &mut used_names))?;
+
+        let header_rows = table.header().len();
+        for (r, row) in table.rows.iter().enumerate() {
+            for (c, value) in row.iter().enumerate() {
+                if r < header_rows {
+                    worksheet.write_string_with_format(r as u32, c as u16, value, &bold)?;
+                } else {
+                    worksheet.write_string(r as u32, c as u16, value)?;
+                }
+            }
+        }
+    }
+
+    workbook.save(path)?;
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use clap::Args;
+use reqwest::{StatusCode, Url};
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+
+use crate::http::Client;
+use crate::input::{self, InputArgs, Source};
+use crate::select::parse_selector;
+use crate::transform;
+use crate::{Extraction, Span, Table};
+
+/// Placeholder for the page number in paginated input URLs.
+const PAGE_PLACEHOLDER: &str = "{page}";
+
+#[derive(Args)]
+pub struct PaginationArgs {
+    /// CSS selector of the link to the next page, e.g. `a[rel=next]`,
+    /// followed from every fetched page
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_selector)]
+    pub follow_next: Option<Selector>,
+
+    /// First page number substituted for `{page}` in input URLs
+    #[arg(long, default_value_t = 1)]
+    pub first_page: usize,
+
+    /// Maximum number of pages fetched for each paginated input
+    #[arg(long, default_value_t = 100)]
+    pub max_pages: usize,
+
+    /// Merge tables with identical header rows from all pages of an input
+    /// into one table
+    #[arg(long)]
+    pub stitch: bool,
+}
+
+impl PaginationArgs {
+    /// Whether any of the sources has to be fetched page by page.
+    pub fn applies_to(&self, sources: &[Source]) -> bool {
+        sources.iter().any(|source| self.is_paginated(source))
+    }
+
+    fn is_paginated(&self, source: &Source) -> bool {
+        source.content.is_none()
+            && input::is_url(&source.location)
+            && (self.follow_next.is_some() || source.location.contains(PAGE_PLACEHOLDER))
+    }
+}
+
+/// Processes the sources one after the other, walking through the pages of
+/// paginated ones. URLs with a `{page}` placeholder are fetched with
+/// increasing page numbers until a page is missing, has no tables or
+/// repeats the previous one; with `--follow-next` the next page is the
+/// target of the matching link. Every page gets its own `page-N` output
+/// directory unless its tables are stitched together.
+pub async fn run(
+    sources: Vec<Source>,
+    args: &PaginationArgs,
+    input_args: &InputArgs,
+    client: &Client,
+    extraction: &mut Extraction<'_>,
+) {
+    for mut source in input::documents(sources) {
+        if args.is_paginated(&source) {
+            paginate(&source, args, input_args, client, extraction).await;
+            continue;
+        }
+
+        match input::load(&mut source, input_args, client).await {
+            Ok(html) => {
+                extraction.process(&source, &html);
+                if input_args.follow_iframes {
+                    extraction.process_frames(input::load_iframes(&source, &html, input_args, client).await);
+                }
+            }
+            Err(err) => extraction.failed(&source, err),
+        }
+    }
+}
+
+async fn paginate(
+    source: &Source,
+    args: &PaginationArgs,
+    input_args: &InputArgs,
+    client: &Client,
+    extraction: &mut Extraction<'_>,
+) {
+    let template = source.location.contains(PAGE_PLACEHOLDER).then_some(source.location.as_str());
+    let page_url = |number: usize| source.location.replace(PAGE_PLACEHOLDER, &number.to_string());
+
+    let mut url = page_url(args.first_page);
+    let mut visited = HashSet::new();
+    let mut previous_rows = None;
+    let mut stitched = Vec::new();
+
+    for page in 1..=args.max_pages {
+        visited.insert(url.clone());
+        // Pages of a template are the input with another number, requested
+        // the same way, while `--follow-next` pages are links like any other
+        let mut page_source = Source {
+            output_subdir: source.output_subdir.join(format!("page-{}", page)),
+            linked: source.linked || (template.is_none() && page > 1),
+            ..Source::new(url.clone())
+        };
+
+        let html = match input::load(&mut page_source, input_args, client).await {
+            Ok(html) => html,
+            // Running past the last page is how a page template ends
+            Err(err) if template.is_some() && page > 1 && is_not_found(&err) => break,
+            Err(err) => {
+                extraction.failed(&page_source, err);
+                break;
+            }
+        };
+        let tables = match extraction
+            .save_document(&page_source, &html)
+            .and_then(|()| extraction.extract(&page_source, &html))
+        {
+            Ok(tables) => tables,
+            Err(err) => {
+                extraction.failed(&page_source, err);
+                break;
+            }
+        };
+
+        let next = if template.is_some() {
+            // Some sites keep serving the last page for any higher number
+            let rows: Vec<Vec<Vec<String>>> = tables.iter().map(|table| table.rows.clone()).collect();
+            if tables.is_empty() || previous_rows.as_ref() == Some(&rows) {
+                break;
+            }
+            previous_rows = Some(rows);
+            Some(page_url(args.first_page + page))
+        } else {
+            args.follow_next
+                .as_ref()
+                .and_then(|selector| {
+                    let base = page_source.final_url.as_deref().unwrap_or(&url);
+                    next_link(&html, base, selector)
+                })
+        };
+
+        if args.stitch {
+            stitch(&mut stitched, tables);
+        } else if let Err(err) = extraction.save(&page_source, tables) {
+            extraction.failed(&page_source, err);
+        }
+
+        match next {
+            Some(next) if !visited.contains(&next) => url = next,
+            _ => break,
+        }
+    }
+
+    if args.stitch && !stitched.is_empty() {
+        if let Err(err) = extraction.save(source, stitched) {
+            extraction.failed(source, err);
+        }
+    }
+}
+
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| status == StatusCode::NOT_FOUND || status == StatusCode::GONE)
+}
+
+/// Absolute URL of the first link matching the selector.
+fn next_link(html: &str, base: &str, selector: &Selector) -> Option<String> {
+    let base = Url::parse(base).ok()?;
+    let document = Html::parse_document(html);
+    let href = document
+        .select(selector)
+        .find_map(|link| link.value().attr("href"))?;
+    let mut next = base.join(href.trim()).ok()?;
+    next.set_fragment(None);
+    Some(next.into())
+}
+
+/// Appends the data rows of every table to an earlier table with the same
+/// header rows, or keeps it as a new table when there is none. Only the
+/// footer rows of the last table appended are kept, at the end.
+pub fn stitch(stitched: &mut Vec<Table>, tables: Vec<Table>) {
+    for table in tables {
+        let Some(existing) = stitched
+            .iter_mut()
+            .find(|existing| existing.header() == table.header())
+        else {
+            stitched.push(table);
+            continue;
+        };
+
+        let footer_start = existing.rows.len() - existing.footer_rows;
+        let keep: Vec<bool> = (0..existing.rows.len()).map(|r| r < footer_start).collect();
+        transform::retain_rows(existing, &keep);
+
+        let header_len = table.header().len();
+        let offset = existing.rows.len() - header_len;
+        existing.spans.extend(
+            table
+                .spans
+                .iter()
+                .filter(|span| span.row >= header_len)
+                .map(|span| Span {
+                    row: span.row + offset,
+                    ..*span
+                }),
+        );
+        existing.footer_rows = table.footer_rows;
+        existing.rows.extend(table.rows.into_iter().skip(header_len));
+    }
+}
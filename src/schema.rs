@@ -0,0 +1,104 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::Table;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    String,
+}
+
+impl ColumnType {
+    fn accepts(self, value: &str) -> bool {
+        match self {
+            // Codes like ZIP codes and phone numbers lose their leading zeros
+            // as numbers
+            ColumnType::Integer | ColumnType::Float if has_leading_zero(value) => false,
+            ColumnType::Integer => value.parse::<i64>().is_ok(),
+            ColumnType::Float => value.parse::<f64>().is_ok_and(f64::is_finite),
+            ColumnType::Boolean => parse_bool(value).is_some(),
+            ColumnType::Date => parse_date(value).is_some(),
+            ColumnType::String => true,
+        }
+    }
+}
+
+/// Whether a number starts with a zero followed by another digit, as in
+/// `00501`, unlike `0` or `0.5`.
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    digits.starts_with('0') && digits[1..].starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// `true` or `false`, in any case.
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// An ISO 8601 calendar date such as `2024-03-01`.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").ok()
+}
+
+/// Infers the narrowest type for every column from the table's data rows.
+/// Empty cells are treated as missing values and don't affect the result;
+/// a column with no values at all is a string column.
+pub fn infer_column_types(table: &Table) -> Vec<ColumnType> {
+    (0..table.width())
+        .map(|col| {
+            let values: Vec<&str> = table
+                .data_rows()
+                .iter()
+                .filter_map(|row| row.get(col))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect();
+
+            if values.is_empty() {
+                return ColumnType::String;
+            }
+            [ColumnType::Integer, ColumnType::Float, ColumnType::Boolean, ColumnType::Date]
+                .into_iter()
+                .find(|ty| values.iter().all(|value| ty.accepts(value)))
+                .unwrap_or(ColumnType::String)
+        })
+        .collect()
+}
+
+/// Column names and inferred types of a table, as written by `--schema-out`.
+pub fn table_schema(table: &Table) -> Value {
+    let columns: Vec<Value> = table
+        .column_names()
+        .into_iter()
+        .zip(infer_column_types(table))
+        .map(|(name, ty)| json!({ "name": name, "type": ty }))
+        .collect();
+    json!({
+        "source": table.source,
+        "table_index": table.index,
+        "caption": table.caption,
+        "columns": columns,
+    })
+}
+
+pub fn write_schemas(schemas: Vec<Value>, path: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, &Value::Array(schemas))?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use anyhow::{bail, Result};
+use clap::Args;
+use dialoguer::MultiSelect;
+use regex::Regex;
+use ego_tree::NodeId;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::IsTerminal;
+use std::ops::RangeInclusive;
+
+use crate::output;
+use crate::Table;
+
+/// Options that narrow down which tables of a document are extracted.
+#[derive(Args)]
+pub struct SelectionArgs {
+    /// Only extract the tables at these 1-based positions in the document,
+    /// e.g. `2`, `1,3-5` or `4-`
+    #[arg(short = 't', long = "tables", visible_alias = "table", value_name = "LIST", value_parser = parse_positions)]
+    pub tables: Option<Positions>,
+
+    /// Only extract tables inside elements matching this CSS selector,
+    /// e.g. `#stats-wrapper`
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_selector)]
+    pub selector: Option<Selector>,
+
+    /// Skip tables inside or matching this CSS selector, e.g.
+    /// `.infobox, .navbox`
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_selector)]
+    pub exclude_selector: Option<Selector>,
+
+    /// Skip tables that only lay out the page rather than hold data, judged
+    /// by their role, structure and content
+    #[arg(long)]
+    pub skip_layout_tables: bool,
+
+    /// Only extract tables with this `id`; can be repeated
+    #[arg(long, value_name = "ID")]
+    pub table_id: Vec<String>,
+
+    /// Only extract tables with this class, e.g. `wikitable`; can be repeated
+    #[arg(long, value_name = "CLASS")]
+    pub table_class: Vec<String>,
+
+    /// Only extract tables whose caption, or nearest preceding heading,
+    /// matches this regex
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub caption_regex: Option<Regex>,
+
+    /// Only extract tables whose header row has all these column names, in
+    /// any order; names are compared case-insensitively
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub require_headers: Vec<String>,
+
+    /// Let `--require-headers` names also match headers that differ in
+    /// punctuation, spacing or a few typos
+    #[arg(long, requires = "require_headers")]
+    pub fuzzy_headers: bool,
+
+    /// Only extract tables with a cell matching this regex
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    pub must_contain: Option<Regex>,
+
+    /// Pick the tables to save from a checklist for every document
+    #[arg(long, conflicts_with = "list")]
+    pub interactive: bool,
+
+    /// Skip tables with the same cells as one already extracted in this run
+    #[arg(long)]
+    pub dedup_tables: bool,
+
+    /// Skip tables with fewer rows than this, header row included
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub min_rows: usize,
+
+    /// Skip tables with fewer columns than this
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub min_cols: usize,
+}
+
+/// Which `<table>` elements of a parsed document the selectors let through.
+pub struct ElementFilter {
+    in_scope: Option<HashSet<NodeId>>,
+    excluded: HashSet<NodeId>,
+    skip_layout: bool,
+}
+
+impl ElementFilter {
+    pub fn allows(&self, table: ElementRef) -> bool {
+        self.in_scope.as_ref().is_none_or(|in_scope| in_scope.contains(&table.id()))
+            && !self.excluded.contains(&table.id())
+            // Only HTML tables are used for page layout
+            && !(self.skip_layout && table.value().name() == "table" && is_layout_table(table))
+    }
+}
+
+impl SelectionArgs {
+    /// The filter for elements matching `tables`, the selector of every
+    /// element extracted as a table.
+    pub fn element_filter(&self, document: &Html, tables: &Selector) -> ElementFilter {
+        ElementFilter {
+            in_scope: self.selector.as_ref().map(|selector| tables_within(document, selector, tables)),
+            excluded: self
+                .exclude_selector
+                .as_ref()
+                .map(|selector| tables_within(document, selector, tables))
+                .unwrap_or_default(),
+            skip_layout: self.skip_layout_tables,
+        }
+    }
+
+    /// Whether an extracted table passes the filters.
+    pub fn matches(&self, table: &Table) -> bool {
+        let position = self.tables.as_ref().is_none_or(|positions| positions.contains(table.index));
+        let id = self.table_id.is_empty()
+            || table.id.as_ref().is_some_and(|id| self.table_id.contains(id));
+        let class = self.table_class.is_empty()
+            || table.classes.iter().any(|class| self.table_class.contains(class));
+        let caption = self.caption_regex.as_ref().is_none_or(|pattern| {
+            [&table.caption, &table.heading]
+                .into_iter()
+                .flatten()
+                .any(|text| pattern.is_match(text))
+        });
+        let headers = self.require_headers.iter().all(|required| {
+            let mut header = table.header().iter().flatten();
+            header.any(|name| header_matches(required, name, self.fuzzy_headers))
+        });
+        let size = table.rows.len() >= self.min_rows && table.width() >= self.min_cols;
+        let content = self.must_contain.as_ref().is_none_or(|pattern| {
+            table.rows.iter().flatten().any(|cell| pattern.is_match(cell))
+        });
+        position && id && class && caption && headers && size && content
+    }
+}
+
+/// Asks which of a document's tables to keep, using a checklist on the
+/// terminal with every table selected to begin with.
+pub fn pick_tables(tables: Vec<Table>, source: &str) -> Result<Vec<Table>> {
+    if tables.is_empty() {
+        return Ok(tables);
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        bail!("--interactive needs a terminal");
+    }
+
+    let items: Vec<String> = tables.iter().map(output::summary).collect();
+    let defaults = vec![true; items.len()];
+    let picked = MultiSelect::new()
+        .with_prompt(format!("Tables to save from {} (space to toggle, enter to confirm)", source))
+        .items(&items)
+        .defaults(&defaults)
+        .interact_opt()?
+        .unwrap_or_default();
+
+    Ok(tables
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picked.contains(i))
+        .map(|(_, table)| table)
+        .collect())
+}
+
+/// Fingerprint of a table's cells, for `--dedup-tables`.
+pub fn content_hash(table: &Table) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    table.rows.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The tables matching a selector or inside elements that match it.
+fn tables_within(document: &Html, selector: &Selector, tables: &Selector) -> HashSet<NodeId> {
+    document
+        .select(selector)
+        .flat_map(|element| {
+            let own = tables.matches(&element).then_some(element);
+            own.into_iter().chain(element.select(tables))
+        })
+        .map(|table| table.id())
+        .collect()
+}
+
+/// Guesses whether a table is only used for layout: it says so with its
+/// role, or it has no caption or header cells of its own and either wraps
+/// other tables or form controls, or is a single row or column.
+fn is_layout_table(table: ElementRef) -> bool {
+    // Elements belonging to this table rather than to a nested one
+    let own = |selector: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        table
+            .select(&selector)
+            .filter(|element| {
+                let parent_table = element.ancestors().filter_map(ElementRef::wrap).find(|ancestor| {
+                    ancestor.value().name() == "table"
+                });
+                parent_table.map(|parent| parent.id()) == Some(table.id())
+            })
+            .collect::<Vec<_>>()
+    };
+
+    if matches!(table.value().attr("role"), Some("presentation" | "none")) {
+        return true;
+    }
+    if !own("caption, thead, th").is_empty() {
+        return false;
+    }
+    let has_nested_table = table.select(&Selector::parse("table").unwrap()).next().is_some();
+    if has_nested_table || !own("input, select, textarea, button").is_empty() {
+        return true;
+    }
+
+    let cell_selector = Selector::parse("td").unwrap();
+    let rows: Vec<usize> = own("tr")
+        .into_iter()
+        .map(|row| row.select(&cell_selector).count())
+        .collect();
+    rows.len() <= 1 || rows.iter().all(|&cells| cells <= 1)
+}
+
+fn header_matches(required: &str, name: &str, fuzzy: bool) -> bool {
+    if !fuzzy {
+        return required.trim().to_lowercase() == name.trim().to_lowercase();
+    }
+
+    // Only letters and digits count; a header may add whole words to the
+    // name (`Close Price` for `Close`) or have about one typo per four
+    // letters, though none in names shorter than five letters
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (required, name) = (words(required), words(name));
+    if required.is_empty() {
+        return false;
+    }
+    if name.windows(required.len()).any(|window| window == required) {
+        return true;
+    }
+    let (required, name): (Vec<char>, Vec<char>) = (required.concat().chars().collect(), name.concat().chars().collect());
+    edit_distance(&required, &name) <= (required.len() - 1) / 4
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// A set of table positions given as numbers and inclusive ranges.
+#[derive(Clone, Debug)]
+pub struct Positions(Vec<RangeInclusive<usize>>);
+
+impl Positions {
+    pub fn contains(&self, position: usize) -> bool {
+        self.0.iter().any(|range| range.contains(&position))
+    }
+}
+
+fn parse_positions(value: &str) -> Result<Positions, String> {
+    let invalid = || format!("invalid table list '{}', expected e.g. 2 or 1,3-5", value);
+    let position = |text: &str| match text.trim().parse::<usize>() {
+        Ok(position) if position > 0 => Ok(position),
+        _ => Err(invalid()),
+    };
+
+    let mut ranges = Vec::new();
+    for part in value.split(',') {
+        let range = match part.split_once('-') {
+            Some((start, "")) => position(start)?..=usize::MAX,
+            Some((start, end)) => position(start)?..=position(end)?,
+            None => position(part)?..=position(part)?,
+        };
+        if range.is_empty() {
+            return Err(invalid());
+        }
+        ranges.push(range);
+    }
+    Ok(Positions(ranges))
+}
+
+pub fn parse_selector(value: &str) -> Result<Selector, String> {
+    Selector::parse(value).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_headers_allow_extra_words_and_typos() {
+        assert!(header_matches("Close", "Close Price", true));
+        assert!(header_matches("close price", "Adj. Close Price", true));
+        assert!(header_matches("Volume", "Volme", true));
+        assert!(header_matches("Population", "Populaton", true));
+    }
+
+    #[test]
+    fn fuzzy_headers_reject_other_words() {
+        assert!(!header_matches("Date", "Rate", true));
+        assert!(!header_matches("Open", "Oven", true));
+        assert!(!header_matches("Open", "Reopened", true));
+        assert!(!header_matches("", "Open", true));
+    }
+}
@@ -0,0 +1,1123 @@
+use clap::{Args, ValueEnum};
+use deunicode::deunicode;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use unicode_normalization::UnicodeNormalization;
+use regex::Regex;
+
+use crate::{Span, Table};
+
+/// Options that reshape the extracted tables.
+#[derive(Args)]
+pub struct TransformArgs {
+    /// Turn non-breaking and other Unicode spaces into plain spaces, and
+    /// remove zero-width characters
+    #[arg(long)]
+    pub clean_spaces: bool,
+
+    /// Collapse runs of whitespace inside cells into single spaces
+    #[arg(long)]
+    pub collapse_whitespace: bool,
+
+    /// Apply this Unicode normalization form to the text of every cell
+    #[arg(long, value_enum, value_name = "FORM")]
+    pub unicode_normalize: Option<NormalizationForm>,
+
+    /// Keep data rows identical to a header row, which long tables often
+    /// repeat for readability and are dropped by default
+    #[arg(long)]
+    pub keep_repeated_headers: bool,
+
+    /// Swap the rows and columns of every table, for tables with their
+    /// headers down the first column
+    #[arg(long)]
+    pub transpose: bool,
+
+    /// Replace data cell values, e.g. `N/A=>,–=>` to blank out sentinel
+    /// values; rules are separated by commas and the option can be
+    /// repeated. A rule `FROM=>TO` replaces cells equal to FROM, and
+    /// `/REGEX/=>TO` replaces every match (with `$1` for groups). A
+    /// `COLUMN::` prefix limits a rule to one column. Write `\,` for a
+    /// comma and `\::` for a `::` that is part of FROM
+    #[arg(long, value_name = "RULES", value_parser = parse_replacements)]
+    pub replace: Vec<Replacements>,
+
+    /// Read replacement rules as for `--replace` from a file, one per line
+    /// with commas taken literally; blank lines and lines starting with `#`
+    /// are ignored
+    #[arg(long, value_name = "PATH", value_parser = parse_replacement_file)]
+    pub replace_file: Option<Replacements>,
+
+    /// Remove rows whose cells are all blank
+    #[arg(long)]
+    pub drop_empty_rows: bool,
+
+    /// Remove columns whose cells are all blank
+    #[arg(long)]
+    pub drop_empty_cols: bool,
+
+    /// Merge the first N rows into one header row of composite column
+    /// names, e.g. `Revenue / 2023`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub header_rows: Option<u16>,
+
+    /// What the cells covered by a merged cell hold: its value, or nothing
+    /// but in its top-left cell
+    #[arg(long, value_enum, default_value_t = SpanFill::Repeat)]
+    pub span_fill: SpanFill,
+
+    /// Rewrite column names as unique ASCII identifiers in this case
+    #[arg(long, value_enum, value_name = "CASE")]
+    pub normalize_headers: Option<HeaderCase>,
+
+    /// Remove currency symbols, percent signs and units of measurement
+    /// around numbers, e.g. `$1,200` or `35 km/h`, in the columns where they
+    /// all share one unit
+    #[arg(long)]
+    pub strip_units: bool,
+
+    /// Move the units removed by `--strip-units` into a `<column>_unit`
+    /// column next to each column that had any
+    #[arg(long, requires = "strip_units")]
+    pub unit_columns: bool,
+
+    /// Rewrite numbers written with this locale's separators, as well as
+    /// negatives in parentheses, as plain numbers like `-1234.56`
+    #[arg(long, value_enum, value_name = "LOCALE")]
+    pub number_locale: Option<NumberLocale>,
+
+    /// Sort the data rows by a column, given by name or 1-based position,
+    /// e.g. `Population:desc`; numbers compare by value. Can be repeated to
+    /// break ties
+    #[arg(long, value_name = "COLUMN[:asc|desc]", value_parser = parse_sort_key)]
+    pub sort_by: Vec<SortKey>,
+
+    /// Drop data rows identical to an earlier one, including across the
+    /// pages of a stitched table and merged tables
+    #[arg(long)]
+    pub dedup_rows: bool,
+
+    /// Only compare these columns, by name or 1-based position, when
+    /// looking for duplicate rows, e.g. `Country,Year`
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',', requires = "dedup_rows")]
+    pub dedup_on: Vec<String>,
+
+    /// Skip the first N data rows of every table
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip_rows: usize,
+
+    /// Keep at most the first N data rows of every table
+    #[arg(long, value_name = "N")]
+    pub head: Option<usize>,
+
+    /// Keep N data rows of every table picked at random, in their original
+    /// order
+    #[arg(long, value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Seed for `--sample`, to pick the same rows on every run
+    #[arg(long, requires = "sample")]
+    pub seed: Option<u64>,
+
+    /// Turn the values of one column into columns, e.g.
+    /// `index=Country,columns=Year,values=GDP` gives one row per country and
+    /// one column per year; columns are given by name or 1-based position
+    #[arg(long, value_name = "SPEC", value_parser = parse_pivot, conflicts_with = "unpivot")]
+    pub pivot: Option<Pivot>,
+
+    /// The reverse of `--pivot`: keep the `index` column and turn every
+    /// other column into a row, with its name in a new `columns` column and
+    /// its value in a new `values` column
+    #[arg(long, value_name = "SPEC", value_parser = parse_pivot)]
+    pub unpivot: Option<Pivot>,
+
+    /// Write every table in long format, one cell per row, with the columns
+    /// `table`, `row`, `column_name` and `value`
+    #[arg(long)]
+    pub long_format: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum NumberLocale {
+    /// `1,234.56`
+    En,
+    /// `1.234,56`
+    De,
+    /// `1 234,56`
+    Fr,
+    /// Guess from the separators of every number
+    Auto,
+}
+
+#[derive(Clone, Debug)]
+pub struct SortKey {
+    column: String,
+    descending: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Replacement {
+    column: Option<String>,
+    pattern: Pattern,
+    replacement: String,
+}
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug)]
+pub struct Replacements(Vec<Replacement>);
+
+#[derive(Clone, Debug)]
+pub struct Pivot {
+    index: String,
+    columns: String,
+    values: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum NormalizationForm {
+    /// Canonical composition, e.g. `e` + U+0301 becomes `é`
+    Nfc,
+    /// Compatibility composition, which also turns `ﬁ` into `fi` and `²`
+    /// into `2`
+    Nfkc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HeaderCase {
+    /// `unit_price`
+    Snake,
+    /// `unitPrice`
+    Camel,
+    /// `unit-price`
+    Kebab,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SpanFill {
+    Repeat,
+    Empty,
+}
+
+impl TransformArgs {
+    pub fn apply(&self, table: &mut Table) {
+        if self.clean_spaces || self.collapse_whitespace || self.unicode_normalize.is_some() {
+            for cell in table.rows.iter_mut().flatten() {
+                *cell = self.clean_text(cell);
+            }
+        }
+        // The later steps need to know every header row for what it is
+        if let Some(count) = self.header_rows {
+            merge_header_rows(table, count as usize);
+        }
+        if !self.keep_repeated_headers {
+            drop_repeated_headers(table);
+        }
+        if self.transpose {
+            transpose(table);
+        }
+        let rules: Vec<&Replacement> = self.replace.iter().chain(&self.replace_file).flat_map(|rules| &rules.0).collect();
+        if !rules.is_empty() {
+            replace_values(table, &rules);
+        }
+        if self.drop_empty_rows {
+            drop_empty_rows(table);
+        }
+        if self.drop_empty_cols {
+            drop_empty_cols(table);
+        }
+        if self.strip_units {
+            strip_units(table, self.unit_columns);
+        }
+        if let Some(locale) = self.number_locale {
+            let header_len = table.header().len();
+            for cell in table.rows[header_len..].iter_mut().flatten() {
+                if let Some(number) = canonical_number(cell, locale) {
+                    *cell = number;
+                }
+            }
+        }
+        self.dedup(table);
+        if !self.sort_by.is_empty() {
+            sort_rows(table, &self.sort_by);
+        }
+        if self.span_fill == SpanFill::Empty {
+            clear_spanned_cells(table);
+        }
+        if let Some(case) = self.normalize_headers {
+            normalize_headers(table, case);
+        }
+        if self.skip_rows > 0 || self.head.is_some() || self.sample.is_some() {
+            self.limit_rows(table);
+        }
+    }
+
+    /// Drops duplicate data rows with `--dedup-rows`, keeping the first of
+    /// each. Tables without one of the `--dedup-on` columns are left alone.
+    pub fn dedup(&self, table: &mut Table) {
+        if !self.dedup_rows {
+            return;
+        }
+        let names = table.column_names();
+        let Some(columns) = self
+            .dedup_on
+            .iter()
+            .map(|column| column_position(&names, column))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            return;
+        };
+
+        let header_len = table.header().len();
+        let mut seen = HashSet::new();
+        let keep: Vec<bool> = table
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(r, row)| {
+                let key: Vec<&str> = if columns.is_empty() {
+                    row.iter().map(String::as_str).collect()
+                } else {
+                    columns.iter().map(|&c| row.get(c).map(String::as_str).unwrap_or_default()).collect()
+                };
+                r < header_len || seen.insert(key)
+            })
+            .collect();
+        retain_rows(table, &keep);
+    }
+
+    /// Changes the shape of a table that was selected, once the selection
+    /// options have seen it as extracted.
+    pub fn reshape(&self, table: &mut Table) {
+        if let Some(pivot) = &self.pivot {
+            pivot_table(table, pivot);
+        }
+        if let Some(unpivot) = &self.unpivot {
+            unpivot_table(table, unpivot);
+        }
+        if self.long_format {
+            to_long_format(table);
+        }
+    }
+
+    fn clean_text(&self, text: &str) -> String {
+        let mut text = match self.unicode_normalize {
+            Some(NormalizationForm::Nfc) => text.nfc().collect(),
+            Some(NormalizationForm::Nfkc) => text.nfkc().collect(),
+            None => text.to_string(),
+        };
+        if self.clean_spaces {
+            text = text
+                .chars()
+                .filter(|&c| !matches!(c, '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{2060}' | '\u{feff}' | '\u{ad}'))
+                .map(|c| if c.is_whitespace() && !matches!(c, '\n' | '\r' | '\t') { ' ' } else { c })
+                .collect();
+        }
+        if self.collapse_whitespace {
+            text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        text
+    }
+
+    /// Skips, then cuts, then samples the data rows of a table.
+    fn limit_rows(&self, table: &mut Table) {
+        let header_len = table.header().len();
+        let mut data: Vec<usize> = (header_len..table.rows.len()).skip(self.skip_rows).collect();
+        if let Some(head) = self.head {
+            data.truncate(head);
+        }
+        if let Some(amount) = self.sample {
+            let mut rng = match self.seed {
+                Some(seed) => fastrand::Rng::with_seed(seed),
+                None => fastrand::Rng::new(),
+            };
+            data = rng.choose_multiple(data, amount);
+            data.sort_unstable();
+        }
+
+        let mut keep = vec![false; table.rows.len()];
+        keep[..header_len].fill(true);
+        for r in data {
+            keep[r] = true;
+        }
+        retain_rows(table, &keep);
+    }
+}
+
+/// Turns the columns of a table into its rows. The first column becomes
+/// the header row.
+fn transpose(table: &mut Table) {
+    let width = table.width();
+    table.rows = (0..width)
+        .map(|c| {
+            table.rows.iter().map(|row| row.get(c).cloned().unwrap_or_default()).collect()
+        })
+        .collect();
+    table.header_rows = 1;
+    table.footer_rows = 0;
+    for span in &mut table.spans {
+        *span = Span {
+            row: span.col,
+            col: span.row,
+            rowspan: span.colspan,
+            colspan: span.rowspan,
+        };
+    }
+}
+
+/// Melts a table into one row per data cell, giving the table's name (with
+/// its part, if split up), the 1-based data row number, the column name and
+/// the cell value.
+fn to_long_format(table: &mut Table) {
+    let mut name = table.label().map(str::to_string).unwrap_or_else(|| format!("table_{}", table.index));
+    if let Some(part) = &table.part {
+        name = format!("{}_{}", name, part);
+    }
+    let names = table.column_names();
+
+    let mut rows = vec![["table", "row", "column_name", "value"].map(str::to_string).to_vec()];
+    for (r, row) in table.data_rows().iter().enumerate() {
+        for (column, value) in names.iter().zip(row) {
+            rows.push(vec![name.clone(), (r + 1).to_string(), column.clone(), value.clone()]);
+        }
+    }
+    table.rows = rows;
+    table.header_rows = 1;
+    table.footer_rows = 0;
+    table.spans.clear();
+}
+
+/// Removes the data rows that repeat one of the header rows, ignoring
+/// surrounding whitespace. Tables without marked up header rows are left
+/// alone, as their first row may well be data.
+fn drop_repeated_headers(table: &mut Table) {
+    let header_len = table.header_rows.min(table.rows.len());
+    let trimmed = |row: &[String]| row.iter().map(|cell| cell.trim().to_string()).collect::<Vec<_>>();
+    let headers: Vec<Vec<String>> = table.rows[..header_len]
+        .iter()
+        .filter(|row| !is_blank(row.iter()))
+        .map(|row| trimmed(row))
+        .collect();
+    if headers.is_empty() {
+        return;
+    }
+
+    let keep: Vec<bool> = table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(r, row)| r < header_len || !headers.contains(&trimmed(row)))
+        .collect();
+    retain_rows(table, &keep);
+}
+
+fn drop_empty_rows(table: &mut Table) {
+    let keep: Vec<bool> = table.rows.iter().map(|row| !is_blank(row.iter())).collect();
+    retain_rows(table, &keep);
+}
+
+/// Removes the rows not kept, shrinking the merged cells that covered them.
+pub fn retain_rows(table: &mut Table, keep: &[bool]) {
+    let header_rows = table.header_rows.min(keep.len());
+    table.header_rows -= keep[..header_rows].iter().filter(|&&kept| !kept).count();
+    let footer_start = keep.len().saturating_sub(table.footer_rows);
+    table.footer_rows = keep[footer_start..].iter().filter(|&&kept| kept).count();
+
+    let mut kept = keep.iter();
+    table.rows.retain(|_| *kept.next().unwrap());
+    table.spans = table
+        .spans
+        .iter()
+        .filter_map(|span| {
+            let (row, rowspan) = kept_range(span.row, span.rowspan, keep)?;
+            Some(Span { row, rowspan, ..*span })
+        })
+        .collect();
+}
+
+fn drop_empty_cols(table: &mut Table) {
+    let keep: Vec<bool> = (0..table.width())
+        .map(|c| !is_blank(table.rows.iter().filter_map(|row| row.get(c))))
+        .collect();
+
+    for row in &mut table.rows {
+        let mut kept = keep.iter();
+        row.retain(|_| kept.next().copied().unwrap_or(false));
+    }
+    table.spans = table
+        .spans
+        .iter()
+        .filter_map(|span| {
+            let (col, colspan) = kept_range(span.col, span.colspan, &keep)?;
+            Some(Span { col, colspan, ..*span })
+        })
+        .collect();
+}
+
+fn is_blank<'a>(mut cells: impl Iterator<Item = &'a String>) -> bool {
+    cells.all(|cell| cell.trim().is_empty())
+}
+
+/// Where a run of `len` rows or columns from `start` ends up once those not
+/// kept are removed, or `None` if none of them are kept.
+fn kept_range(start: usize, len: usize, keep: &[bool]) -> Option<(usize, usize)> {
+    let new_start = keep[..start].iter().filter(|&&kept| kept).count();
+    let new_len = keep[start..start + len].iter().filter(|&&kept| kept).count();
+    (new_len > 0).then_some((new_start, new_len))
+}
+
+/// Replaces the first `count` rows with a single row naming every column
+/// after its header cells from top to bottom. Merged cells are repeated over
+/// the columns and rows they cover, so a name only appears once per column.
+fn merge_header_rows(table: &mut Table, count: usize) {
+    let count = count.min(table.rows.len());
+    if count == 0 {
+        return;
+    }
+
+    let header: Vec<Vec<String>> = table.rows.drain(..count).collect();
+    let names = (0..table.width().max(header.iter().map(Vec::len).max().unwrap_or(0)))
+        .map(|c| {
+            let mut parts: Vec<&str> = Vec::new();
+            for part in header.iter().filter_map(|row| row.get(c)).map(|cell| cell.trim()) {
+                if !part.is_empty() && parts.last() != Some(&part) {
+                    parts.push(part);
+                }
+            }
+            parts.join(" / ")
+        })
+        .collect();
+    table.rows.insert(0, names);
+    table.header_rows = 1;
+
+    // Merged cells of the header are gone, and the rows below move up
+    table.spans = table
+        .spans
+        .iter()
+        .filter(|span| span.row >= count)
+        .map(|span| Span {
+            row: span.row - count + 1,
+            ..*span
+        })
+        .collect();
+}
+
+/// Empties the cells covered by merged cells, keeping the value in the
+/// top-left cell of each.
+fn clear_spanned_cells(table: &mut Table) {
+    for span in &table.spans {
+        for r in span.row..span.row + span.rowspan {
+            for c in span.col..span.col + span.colspan {
+                if (r, c) == (span.row, span.col) {
+                    continue;
+                }
+                if let Some(cell) = table.rows.get_mut(r).and_then(|row| row.get_mut(c)) {
+                    cell.clear();
+                }
+            }
+        }
+    }
+}
+
+/// A number with a currency symbol or code before it, or a symbol or unit
+/// after it. Word-like units are checked against `UNITS`.
+static WITH_UNIT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?<sign>[-−])?(?:(?<prefix>[$€£¥₹₩₽¢]|[A-Z]{3}\s|[A-Z]{1,2}\$)\s*)?(?<number>[-−+(]?\d(?:[\d.,\s]*\d)?\)?)\s*(?<suffix>[%‰$€£¥₹₩₽¢]|[A-Za-zµ°]+[²³]?(?:/[A-Za-zµ°]+[²³]?)?)?$",
+    )
+    .unwrap()
+});
+
+/// Currency codes written before amounts, e.g. `USD 12`.
+const CURRENCY_CODES: [&str; 16] = [
+    "USD", "EUR", "GBP", "JPY", "CNY", "INR", "CHF", "CAD", "AUD", "NZD", "SEK", "NOK", "DKK", "BRL", "MXN", "ZAR",
+];
+
+/// SI and imperial units stripped after numbers, also as the parts of
+/// compound units like `km/h` and with `²`/`³`.
+const UNITS: [&str; 69] = [
+    "nm", "µm", "mm", "cm", "m", "km", "in", "ft", "yd", "mi", "nmi", "mg", "g", "kg", "t", "lb", "lbs", "oz",
+    "ml", "mL", "cl", "l", "L", "gal", "pt", "qt", "ms", "s", "sec", "min", "h", "hr", "hrs", "mph", "kph", "kn",
+    "W", "kW", "MW", "GW", "Wh", "kWh", "MWh", "GWh", "V", "kV", "A", "mA", "Hz", "kHz", "MHz", "GHz", "B", "kB",
+    "KB", "MB", "GB", "TB", "Pa", "kPa", "hPa", "bar", "psi", "J", "kJ", "cal", "kcal", "°C", "°F",
+];
+
+/// Whether a suffix after a number is a symbol or known unit, rather than a
+/// word or an ordinal like the `st` of `1st`.
+fn is_unit(suffix: &str) -> bool {
+    let known = |unit: &str| UNITS.contains(&unit.trim_end_matches(['²', '³'])) || unit == "°";
+    match suffix.split_once('/') {
+        Some((unit, per)) => known(unit) && known(per),
+        None => (suffix.chars().count() == 1 && !suffix.chars().all(char::is_alphanumeric)) || known(suffix),
+    }
+}
+
+/// Removes the units around numbers in the data rows, adding the columns
+/// to hold them when `unit_columns` is set. Columns whose numbers come with
+/// different units, or some with a unit and some with an unknown word, are
+/// left as they are.
+fn strip_units(table: &mut Table, unit_columns: bool) {
+    let header_len = table.header().len();
+    let width = table.width();
+    let mut units: Vec<Vec<String>> = vec![vec![String::new(); width]; table.rows.len()];
+    let mut stripped: Vec<Vec<Option<String>>> = vec![vec![None; width]; table.rows.len()];
+    // The one unit of each column, or `None` once they disagree
+    let mut column_units: Vec<Option<Option<String>>> = vec![Some(None); width];
+
+    for (r, row) in table.rows.iter().enumerate().skip(header_len) {
+        for (c, cell) in row.iter().enumerate() {
+            let Some(captures) = WITH_UNIT.captures(cell.trim()) else {
+                continue;
+            };
+            let unit = match (captures.name("prefix"), captures.name("suffix")) {
+                (Some(unit), None) if unit.as_str().ends_with(char::is_whitespace) => {
+                    let code = unit.as_str().trim();
+                    if !CURRENCY_CODES.contains(&code) {
+                        column_units[c] = None;
+                        continue;
+                    }
+                    code
+                }
+                (Some(unit), None) => unit.as_str(),
+                (None, Some(unit)) if is_unit(unit.as_str()) => unit.as_str(),
+                (None, None) => continue,
+                _ => {
+                    column_units[c] = None;
+                    continue;
+                }
+            };
+            match &column_units[c] {
+                Some(None) => column_units[c] = Some(Some(unit.to_string())),
+                Some(Some(shared)) if shared != unit => column_units[c] = None,
+                _ => {}
+            }
+            units[r][c] = unit.to_string();
+            stripped[r][c] = Some(captures.name("sign").map_or("", |sign| sign.as_str()).to_string() + &captures["number"]);
+        }
+    }
+
+    for (r, row) in table.rows.iter_mut().enumerate() {
+        for (c, cell) in row.iter_mut().enumerate() {
+            if column_units[c].is_none() {
+                units[r][c].clear();
+            } else if let Some(number) = stripped[r][c].take() {
+                *cell = number;
+            }
+        }
+    }
+    if unit_columns {
+        insert_companion_columns(table, units, "unit");
+    }
+}
+
+/// Adds a `<column>_<suffix>` column after every column with any value in
+/// `values`, a grid of the table's size holding the companion column values
+/// of its data rows.
+pub fn insert_companion_columns(table: &mut Table, mut values: Vec<Vec<String>>, suffix: &str) {
+    let header_len = table.header().len();
+    let width = table.width();
+    let names = table.column_names();
+
+    for c in (0..width).rev() {
+        if values.iter().skip(header_len).all(|row| row.get(c).is_none_or(String::is_empty)) {
+            continue;
+        }
+        for (r, row) in table.rows.iter_mut().enumerate() {
+            row.resize(width.max(row.len()), String::new());
+            let value = match r {
+                _ if r + 1 == header_len => format!("{}_{}", names[c], suffix),
+                _ if r < header_len => row[c].clone(),
+                _ => values.get_mut(r).and_then(|row| row.get_mut(c)).map(std::mem::take).unwrap_or_default(),
+            };
+            row.insert(c + 1, value);
+        }
+        for span in &mut table.spans {
+            if span.col > c {
+                span.col += 1;
+            } else if span.col + span.colspan > c + 1 {
+                span.colspan += 1;
+            }
+        }
+    }
+}
+
+/// Adds a first column named `name` holding `values` in the data rows, one
+/// per row of the table.
+pub fn insert_first_column(table: &mut Table, name: &str, mut values: Vec<String>) {
+    let header_len = table.header().len();
+    values.resize(table.rows.len(), String::new());
+    for value in &mut values[..header_len] {
+        value.clear();
+    }
+    if header_len > 0 {
+        values[header_len - 1] = name.to_string();
+    }
+
+    for (row, value) in table.rows.iter_mut().zip(values) {
+        row.insert(0, value);
+    }
+    for span in &mut table.spans {
+        span.col += 1;
+    }
+}
+
+/// Spaces used to group digits, thin and non-breaking ones included, which
+/// are accepted in every locale.
+const GROUP_SPACES: [char; 4] = [' ', '\u{a0}', '\u{2009}', '\u{202f}'];
+
+/// A number written in `locale` as a plain number, or `None` if the text
+/// isn't one.
+fn canonical_number(text: &str, locale: NumberLocale) -> Option<String> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('(').and_then(|text| text.strip_suffix(')')) {
+        Some(inner) => (true, inner.trim()),
+        None => match text.strip_prefix(['-', '\u{2212}']) {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        },
+    };
+
+    let locale = match locale {
+        NumberLocale::Auto => guess_locale(text),
+        locale => locale,
+    };
+    let (group, decimal) = match locale {
+        NumberLocale::De => (Some('.'), ','),
+        NumberLocale::Fr => (None, ','),
+        _ => (Some(','), '.'),
+    };
+
+    let (integer, fraction) = match text.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+    let groups: Vec<&str> = integer.split(|c| Some(c) == group || GROUP_SPACES.contains(&c)).collect();
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let grouped = groups.len() == 1
+        || (matches!(groups[0].len(), 1..=3) && groups[1..].iter().all(|group| group.len() == 3));
+    if !grouped || !groups.iter().all(|group| digits(group)) || !fraction.is_none_or(|part| !part.is_empty() && digits(part)) {
+        return None;
+    }
+    let integer = groups.concat();
+    if integer.is_empty() && fraction.is_none() {
+        return None;
+    }
+
+    let mut number = String::new();
+    if negative {
+        number.push('-');
+    }
+    number.push_str(if integer.is_empty() { "0" } else { &integer });
+    if let Some(fraction) = fraction {
+        number.push('.');
+        number.push_str(fraction);
+    }
+    Some(number)
+}
+
+/// The locale a number is most likely written in: the last of `.` and `,`
+/// is the decimal separator when both appear, and a lone separator groups
+/// digits only if it repeats or is followed by exactly three digits.
+fn guess_locale(text: &str) -> NumberLocale {
+    match (text.rfind('.'), text.rfind(',')) {
+        (Some(dot), Some(comma)) if comma > dot => NumberLocale::De,
+        (Some(_), Some(_)) => NumberLocale::En,
+        (Some(_), None) if text.matches('.').count() > 1 => NumberLocale::De,
+        (None, Some(comma)) if text.matches(',').count() == 1 && text.len() - comma - 1 != 3 => NumberLocale::De,
+        _ => NumberLocale::En,
+    }
+}
+
+fn parse_sort_key(value: &str) -> Result<SortKey, String> {
+    let (column, descending) = match value.rsplit_once(':') {
+        Some((column, "asc")) => (column, false),
+        Some((column, "desc")) => (column, true),
+        _ => (value, false),
+    };
+    if column.trim().is_empty() {
+        return Err("missing column".to_string());
+    }
+    Ok(SortKey {
+        column: column.trim().to_string(),
+        descending,
+    })
+}
+
+/// Splits a `--replace` value into its rules at the commas that aren't
+/// escaped as `\,`.
+fn parse_replacements(value: &str) -> Result<Replacements, String> {
+    let mut rules = Vec::new();
+    let mut rule = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.as_str().starts_with(',') => {
+                rule.push(',');
+                chars.next();
+            }
+            ',' => rules.push(parse_replacement(&std::mem::take(&mut rule))?),
+            c => rule.push(c),
+        }
+    }
+    rules.push(parse_replacement(&rule)?);
+    Ok(Replacements(rules))
+}
+
+fn parse_replacement(value: &str) -> Result<Replacement, String> {
+    let Some((from, replacement)) = value.split_once("=>") else {
+        return Err(format!("expected FROM=>TO, got '{}'", value));
+    };
+    if replacement.contains("=>") {
+        return Err(format!("expected one FROM=>TO, got '{}'", value));
+    }
+    // The column prefix ends at the first `::` that isn't escaped
+    let prefix_end = from.match_indices("::").map(|(i, _)| i).find(|&i| !from[..i].ends_with('\\'));
+    let (column, from) = match prefix_end {
+        Some(i) => (Some(from[..i].trim().to_string()), &from[i + 2..]),
+        None => (None, from),
+    };
+    let from = from.replace("\\::", "::");
+    let pattern = match from.strip_prefix('/').and_then(|from| from.strip_suffix('/')) {
+        Some(regex) => Pattern::Regex(Regex::new(regex).map_err(|err| err.to_string())?),
+        None => Pattern::Literal(from.trim().to_string()),
+    };
+    Ok(Replacement {
+        column,
+        pattern,
+        replacement: replacement.to_string(),
+    })
+}
+
+fn parse_replacement_file(path: &str) -> Result<Replacements, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| parse_replacement(line).map_err(|err| format!("line {}: {}", number + 1, err)))
+        .collect::<Result<_, _>>()
+        .map(Replacements)
+}
+
+/// Applies the replacement rules to the data cells, in order. Rules for a
+/// column the table doesn't have are skipped.
+fn replace_values(table: &mut Table, rules: &[&Replacement]) {
+    let names = table.column_names();
+    let header_len = table.header().len();
+
+    for rule in rules {
+        let column = match &rule.column {
+            Some(column) => match column_position(&names, column) {
+                Some(position) => Some(position),
+                None => continue,
+            },
+            None => None,
+        };
+        for row in &mut table.rows[header_len..] {
+            for (c, cell) in row.iter_mut().enumerate() {
+                if column.is_some_and(|column| column != c) {
+                    continue;
+                }
+                match &rule.pattern {
+                    Pattern::Literal(from) if cell.trim() == from => cell.clone_from(&rule.replacement),
+                    Pattern::Literal(_) => {}
+                    Pattern::Regex(regex) => *cell = regex.replace_all(cell, &rule.replacement).into_owned(),
+                }
+            }
+        }
+    }
+}
+
+/// Position of a column given by name, ignoring case, or by 1-based
+/// position.
+fn column_position(names: &[String], column: &str) -> Option<usize> {
+    names
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(column))
+        .or_else(|| column.parse::<usize>().ok().filter(|&c| c >= 1 && c <= names.len()).map(|c| c - 1))
+}
+
+fn parse_pivot(value: &str) -> Result<Pivot, String> {
+    let mut fields = [("index", None), ("columns", None), ("values", None)];
+    for part in value.split(',') {
+        let Some((key, column)) = part.split_once('=') else {
+            return Err(format!("expected KEY=COLUMN, got '{}'", part));
+        };
+        let Some((_, field)) = fields.iter_mut().find(|(name, _)| *name == key.trim()) else {
+            return Err(format!("unknown key '{}', expected index, columns or values", key.trim()));
+        };
+        *field = Some(column.trim().to_string()).filter(|column| !column.is_empty());
+    }
+    match fields {
+        [(_, Some(index)), (_, Some(columns)), (_, Some(values))] => Ok(Pivot { index, columns, values }),
+        _ => Err("index, columns and values are all required".to_string()),
+    }
+}
+
+/// Makes a row for each distinct value of the index column and a column for
+/// each distinct value of the columns column, in the order they first
+/// appear, holding the last matching value. Tables without one of the
+/// columns are left alone.
+fn pivot_table(table: &mut Table, pivot: &Pivot) {
+    let names = table.column_names();
+    let (Some(index), Some(columns), Some(values)) = (
+        column_position(&names, &pivot.index),
+        column_position(&names, &pivot.columns),
+        column_position(&names, &pivot.values),
+    ) else {
+        return;
+    };
+
+    let cell = |row: &[String], c: usize| row.get(c).cloned().unwrap_or_default();
+    let mut header = vec![names[index].clone()];
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for row in table.data_rows() {
+        let key = cell(row, index);
+        let column = cell(row, columns);
+        let c = match header[1..].iter().position(|name| *name == column) {
+            Some(c) => c + 1,
+            None => {
+                header.push(column);
+                header.len() - 1
+            }
+        };
+        let r = match rows.iter().position(|existing| existing[0] == key) {
+            Some(r) => r,
+            None => {
+                rows.push(vec![key]);
+                rows.len() - 1
+            }
+        };
+        let pivoted = &mut rows[r];
+        if pivoted.len() <= c {
+            pivoted.resize(c + 1, String::new());
+        }
+        pivoted[c] = cell(row, values);
+    }
+
+    for row in &mut rows {
+        row.resize(header.len(), String::new());
+    }
+    rows.insert(0, header);
+    table.rows = rows;
+    table.header_rows = 1;
+    table.footer_rows = 0;
+    table.spans.clear();
+}
+
+/// Makes a row for each cell of the data rows outside the index column,
+/// holding the index value, the cell's column name and its value. Tables
+/// without the index column are left alone.
+fn unpivot_table(table: &mut Table, unpivot: &Pivot) {
+    let names = table.column_names();
+    let Some(index) = column_position(&names, &unpivot.index) else {
+        return;
+    };
+
+    let mut rows = vec![vec![names[index].clone(), unpivot.columns.clone(), unpivot.values.clone()]];
+    for row in table.data_rows() {
+        let key = row.get(index).cloned().unwrap_or_default();
+        for (c, name) in names.iter().enumerate().filter(|&(c, _)| c != index) {
+            rows.push(vec![key.clone(), name.clone(), row.get(c).cloned().unwrap_or_default()]);
+        }
+    }
+    table.rows = rows;
+    table.header_rows = 1;
+    table.footer_rows = 0;
+    table.spans.clear();
+}
+
+/// Sorts the data rows of a table by the given keys, leaving tables without
+/// one of the columns alone. Footer rows keep their place at the end. Merged
+/// cells among the data rows can't survive reordering, so they are dropped.
+fn sort_rows(table: &mut Table, keys: &[SortKey]) {
+    let names = table.column_names();
+    let mut columns = Vec::new();
+    for key in keys {
+        match column_position(&names, &key.column) {
+            Some(column) => columns.push((column, key.descending)),
+            None => return,
+        }
+    }
+
+    let header_len = table.header().len();
+    let data_end = table.rows.len() - table.footer_rows.min(table.rows.len() - header_len);
+    let cell = |row: &[String], column: usize| row.get(column).map(String::as_str).unwrap_or_default().to_string();
+    table.rows[header_len..data_end].sort_by(|a, b| {
+        columns.iter().fold(Ordering::Equal, |ordering, &(column, descending)| {
+            ordering.then_with(|| compare_cells(&cell(a, column), &cell(b, column), descending))
+        })
+    });
+    table.spans.retain(|span| span.row + span.rowspan <= header_len || span.row >= data_end);
+}
+
+/// Orders numbers by value, then text case-insensitively, then blank cells.
+/// Sorting in descending order keeps that grouping.
+fn compare_cells(a: &str, b: &str, descending: bool) -> Ordering {
+    let key = |cell: &str| {
+        let cell = cell.trim();
+        // `NaN` and `inf` are words here, not numbers
+        match cell.replace(',', "").parse::<f64>().ok().filter(|number| number.is_finite()) {
+            _ if cell.is_empty() => (2, None, String::new()),
+            Some(number) => (0, Some(number), String::new()),
+            None => (1, None, cell.to_lowercase()),
+        }
+    };
+    let (a, b) = (key(a), key(b));
+    let ordering = a.1.zip(b.1).map_or_else(|| a.2.cmp(&b.2), |(x, y)| x.total_cmp(&y));
+    a.0.cmp(&b.0).then(if descending { ordering.reverse() } else { ordering })
+}
+
+/// Rewrites the last header row as identifiers: transliterated to ASCII,
+/// split into words at anything but letters and digits, and joined in
+/// `case`. Blank names become `column_N` and repeated ones get a numeric
+/// suffix.
+fn normalize_headers(table: &mut Table, case: HeaderCase) {
+    let width = table.width();
+    let Some(header) = table.header().len().checked_sub(1) else {
+        return;
+    };
+    let separator = match case {
+        HeaderCase::Snake => "_",
+        HeaderCase::Camel => "",
+        HeaderCase::Kebab => "-",
+    };
+
+    let row = &mut table.rows[header];
+    row.resize(width, String::new());
+    let mut used = HashSet::new();
+    for (c, name) in row.iter_mut().enumerate() {
+        let words: Vec<String> = deunicode(name)
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let base = match case {
+            _ if words.is_empty() => ["column", &(c + 1).to_string()].join(separator),
+            HeaderCase::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) })
+                .collect(),
+            _ => words.join(separator),
+        };
+
+        let mut unique = base.clone();
+        let mut counter = 2;
+        while !used.insert(unique.clone()) {
+            unique = format!("{}{}{}", base, separator, counter);
+            counter += 1;
+        }
+        *name = unique;
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(rule: &Replacement) -> &str {
+        match &rule.pattern {
+            Pattern::Literal(from) => from,
+            Pattern::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    #[test]
+    fn numbers_in_each_locale() {
+        assert_eq!(canonical_number("1,234.56", NumberLocale::En).as_deref(), Some("1234.56"));
+        assert_eq!(canonical_number("12,345,678", NumberLocale::En).as_deref(), Some("12345678"));
+        assert_eq!(canonical_number("1.234,56", NumberLocale::De).as_deref(), Some("1234.56"));
+        assert_eq!(canonical_number("1.234", NumberLocale::De).as_deref(), Some("1234"));
+        assert_eq!(canonical_number("1 234,56", NumberLocale::Fr).as_deref(), Some("1234.56"));
+        assert_eq!(canonical_number("1\u{202f}234\u{a0}567", NumberLocale::Fr).as_deref(), Some("1234567"));
+    }
+
+    #[test]
+    fn numbers_with_signs() {
+        assert_eq!(canonical_number("(1,234)", NumberLocale::En).as_deref(), Some("-1234"));
+        assert_eq!(canonical_number("\u{2212}5", NumberLocale::En).as_deref(), Some("-5"));
+        assert_eq!(canonical_number("-0,5", NumberLocale::De).as_deref(), Some("-0.5"));
+        assert_eq!(canonical_number("+7", NumberLocale::En).as_deref(), Some("7"));
+        assert_eq!(canonical_number(".5", NumberLocale::En).as_deref(), Some("0.5"));
+    }
+
+    #[test]
+    fn numbers_guessing_the_locale() {
+        assert_eq!(canonical_number("1.234,56", NumberLocale::Auto).as_deref(), Some("1234.56"));
+        assert_eq!(canonical_number("1,234.56", NumberLocale::Auto).as_deref(), Some("1234.56"));
+        assert_eq!(canonical_number("1,234", NumberLocale::Auto).as_deref(), Some("1234"));
+        assert_eq!(canonical_number("3,5", NumberLocale::Auto).as_deref(), Some("3.5"));
+        assert_eq!(canonical_number("1.234.567", NumberLocale::Auto).as_deref(), Some("1234567"));
+        assert_eq!(canonical_number("1.5", NumberLocale::Auto).as_deref(), Some("1.5"));
+    }
+
+    #[test]
+    fn numbers_reject_other_text() {
+        assert_eq!(canonical_number("1,23", NumberLocale::En), None);
+        assert_eq!(canonical_number("1234,567.8", NumberLocale::En), None);
+        assert_eq!(canonical_number("1.", NumberLocale::En), None);
+        assert_eq!(canonical_number("-", NumberLocale::En), None);
+        assert_eq!(canonical_number("12 apples", NumberLocale::Fr), None);
+        assert_eq!(canonical_number("", NumberLocale::Auto), None);
+    }
+
+    #[test]
+    fn sort_keeps_words_like_nan_out_of_numbers() {
+        let mut cells = vec!["10", "NaN", "2", "inf", "", "-Infinity", "1,000"];
+        cells.sort_by(|a, b| compare_cells(a, b, false));
+        assert_eq!(cells, ["2", "10", "1,000", "-Infinity", "inf", "NaN", ""]);
+        cells.sort_by(|a, b| compare_cells(a, b, true));
+        assert_eq!(cells, ["1,000", "10", "2", "NaN", "inf", "-Infinity", ""]);
+    }
+
+    #[test]
+    fn replace_splits_rules_at_commas() {
+        let rules = parse_replacements("N/A=>,–=>,n.a.=>").unwrap().0;
+        let froms: Vec<&str> = rules.iter().map(literal).collect();
+        assert_eq!(froms, ["N/A", "–", "n.a."]);
+        assert!(rules.iter().all(|rule| rule.replacement.is_empty() && rule.column.is_none()));
+    }
+
+    #[test]
+    fn replace_keeps_escaped_commas() {
+        let rules = parse_replacements(r"1\,000=>1000,/(\d)\,(\d)/=>$1$2").unwrap().0;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(literal(&rules[0]), "1,000");
+        assert!(matches!(&rules[1].pattern, Pattern::Regex(regex) if regex.as_str() == r"(\d),(\d)"));
+        assert_eq!(rules[1].replacement, "$1$2");
+    }
+
+    #[test]
+    fn replace_reads_column_prefix() {
+        let rule = parse_replacement("Price::-=>0").unwrap();
+        assert_eq!(rule.column.as_deref(), Some("Price"));
+        assert_eq!(literal(&rule), "-");
+        assert_eq!(rule.replacement, "0");
+
+        let rule = parse_replacement(r"a\::b=>c").unwrap();
+        assert_eq!(rule.column, None);
+        assert_eq!(literal(&rule), "a::b");
+    }
+
+    #[test]
+    fn replace_rejects_malformed_rules() {
+        assert!(parse_replacement("N/A").is_err());
+        assert!(parse_replacement("a=>b=>c").is_err());
+        assert!(parse_replacements("N/A=>,oops").is_err());
+        assert!(parse_replacement("/(/=>x").is_err());
+    }
+}